@@ -0,0 +1,119 @@
+//! Compare binding rows with per-column `Binder`s against boxing every cell
+//! as a `dyn ToSql`, which is how rows used to be translated.
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use pg_to_sqlite3::pg::{Binder, CellSource};
+use postgres_types::{FromSql, Type};
+use rusqlite::{types::ToSqlOutput, Connection, ToSql};
+
+const ROWS: usize = 10_000;
+
+/// A row of cells in postgres' binary format, as they'd arrive from the server.
+struct Cells {
+    types: Vec<Type>,
+    cells: Vec<Option<Vec<u8>>>,
+}
+
+impl CellSource for Cells {
+    fn try_get_cell<'a, T: FromSql<'a>>(&'a self, index: usize) -> Result<T, postgres::Error> {
+        return Ok(T::from_sql_nullable(&self.types[index], self.cells[index].as_deref()).unwrap());
+    }
+}
+
+fn sample_row(n: usize) -> Cells {
+    let micros_since_2000 = 631_152_000_000_000 + n as i64 * 1_000_000;
+    return Cells {
+        types: vec![
+            Type::INT8,
+            Type::TEXT,
+            Type::FLOAT8,
+            Type::TIMESTAMP,
+            Type::BOOL,
+            Type::BYTEA,
+            Type::INT4,
+            Type::TEXT,
+        ],
+        cells: vec![
+            Some((n as i64).to_be_bytes().to_vec()),
+            Some(format!("/usr/share/file-{}.txt", n).into_bytes()),
+            Some((n as f64 / 7.0).to_be_bytes().to_vec()),
+            Some(micros_since_2000.to_be_bytes().to_vec()),
+            Some(vec![(n % 2) as u8]),
+            Some(vec![0xde, 0xad, 0xbe, 0xef]),
+            Some((n as i32).to_be_bytes().to_vec()),
+            None,
+        ],
+    };
+}
+
+fn boxed_cells(row: &Cells) -> Vec<Box<dyn ToSql>> {
+    let mut cells: Vec<Box<dyn ToSql>> = vec![];
+    for (index, pg_type) in row.types.iter().enumerate() {
+        let cell: Box<dyn ToSql> = match pg_type {
+            &Type::INT8 => Box::new(row.try_get_cell::<Option<i64>>(index).unwrap()),
+            &Type::INT4 => Box::new(row.try_get_cell::<Option<i32>>(index).unwrap()),
+            &Type::FLOAT8 => Box::new(row.try_get_cell::<Option<f64>>(index).unwrap()),
+            &Type::BOOL => Box::new(row.try_get_cell::<Option<bool>>(index).unwrap()),
+            &Type::BYTEA => Box::new(row.try_get_cell::<Option<Vec<u8>>>(index).unwrap()),
+            &Type::TEXT => Box::new(row.try_get_cell::<Option<String>>(index).unwrap()),
+            &Type::TIMESTAMP => Box::new(
+                row.try_get_cell::<Option<chrono::NaiveDateTime>>(index)
+                    .unwrap(),
+            ),
+            other => panic!("no sample cells of type {}", other),
+        };
+        cells.push(cell);
+    }
+    return cells;
+}
+
+fn bench_bind_rows(c: &mut Criterion) {
+    let rows: Vec<Cells> = (0..ROWS).map(sample_row).collect();
+    let mut lite = Connection::open_in_memory().unwrap();
+    lite.execute_batch(
+        "CREATE TABLE t (a INTEGER, b TEXT, c REAL, d REAL, e INTEGER, f BLOB, g INTEGER, h TEXT)",
+    )
+    .unwrap();
+    let insert = "INSERT INTO t VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
+
+    let mut group = c.benchmark_group("bind_rows");
+    group.throughput(Throughput::Elements(ROWS as u64));
+    group.bench_function("boxed cells", |b| {
+        b.iter(|| {
+            let txn = lite.transaction().unwrap();
+            {
+                let mut statement = txn.prepare(insert).unwrap();
+                for row in &rows {
+                    statement.execute(boxed_cells(row)).unwrap();
+                }
+            }
+            txn.rollback().unwrap();
+        })
+    });
+    group.bench_function("binders", |b| {
+        b.iter(|| {
+            let txn = lite.transaction().unwrap();
+            {
+                let mut statement = txn.prepare(insert).unwrap();
+                let mut binders: Vec<Binder> = rows[0]
+                    .types
+                    .iter()
+                    .map(|t| Binder::new(t).unwrap())
+                    .collect();
+                for row in &rows {
+                    for (index, binder) in binders.iter_mut().enumerate() {
+                        let cell = binder.cell(row, index).unwrap();
+                        statement
+                            .raw_bind_parameter(index + 1, ToSqlOutput::Borrowed(cell))
+                            .unwrap();
+                    }
+                    statement.raw_execute().unwrap();
+                }
+            }
+            txn.rollback().unwrap();
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_bind_rows);
+criterion_main!(benches);
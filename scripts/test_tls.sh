@@ -0,0 +1,7 @@
+#!/bin/sh
+# run the TLS integration tests against the `tls_db` docker-compose service
+set -e
+docker-compose up -d --build tls_db
+sleep 5
+docker cp "$(docker-compose ps -q tls_db)":/var/lib/postgresql/server.crt ./target/tls_db_root.crt
+PG_TLS_TEST_ROOTCERT=./target/tls_db_root.crt cargo test --test tls -- --ignored
@@ -1,4 +1,42 @@
-use clap::{App, Arg, ArgGroup};
+use crate::pg::{
+    NumericColumn, Pattern, ARRAY_POLICIES, DATETIME_POLICIES, EXCLUDED_FK_POLICIES, EXTRACTORS,
+    FOREIGN_TABLE_POLICIES, INHERITANCE_POLICIES, MATERIALIZED_VIEW_POLICIES, NAMING_POLICIES,
+    NUMERIC_POLICIES, PARTITION_POLICIES, SSL_MODES, TIMESTAMPTZ_POLICIES, UNTRANSLATABLE_POLICIES,
+};
+use crate::sqlite::{self, FINAL_JOURNAL_MODES};
+use clap::{App, Arg, ArgGroup, ArgMatches, Error, ErrorKind};
+
+fn validate_pattern(pattern: String) -> Result<(), String> {
+    return Pattern::parse(&pattern).map(|_| ());
+}
+
+fn validate_jobs(jobs: String) -> Result<(), String> {
+    match jobs.parse::<usize>() {
+        Ok(n) if n > 0 => return Ok(()),
+        _ => return Err(format!("expected a positive number of jobs, got {}", jobs)),
+    }
+}
+
+fn validate_pragma(pragma: String) -> Result<(), String> {
+    return sqlite::parse_pragma(&pragma).map(|_| ());
+}
+
+fn validate_numeric_column(column: String) -> Result<(), String> {
+    return NumericColumn::parse(&column).map(|_| ());
+}
+
+/// Check what clap can't: `--partition-column` names the partition a
+/// merged row came from, so it conflicts with `--partitions separate` but
+/// not with `--partitions merge`.
+pub fn check(matches: &ArgMatches) -> Result<(), Error> {
+    if matches.is_present("partition_column") && matches.value_of("partitions") != Some("merge") {
+        return Err(Error::with_description(
+            "--partition-column can't be used with --partitions separate",
+            ErrorKind::ArgumentConflict,
+        ));
+    }
+    return Ok(());
+}
 
 pub fn new<'a>() -> App<'a, 'a> {
     let result = App::new("pg-to-sqlite3")
@@ -9,8 +47,38 @@ pub fn new<'a>() -> App<'a, 'a> {
             Arg::with_name("SRC")
                 .long("src")
                 .takes_value(true)
-                .required(true)
-                .help("a postgres connection string to your source database"),
+                .help("a postgres connection string to your source database")
+                .long_help(
+                    "a postgres connection string to your source database. Parameters it \
+                    leaves unset are read from the pg_service.conf entry named by `service`, \
+                    then PG* environment variables; a missing password is looked up in \
+                    ~/.pgpass",
+                ),
+        )
+        .arg(
+            Arg::with_name("sslmode")
+                .long("sslmode")
+                .takes_value(true)
+                .possible_values(SSL_MODES)
+                .help("whether and how to secure the connection with TLS; defaults to 'prefer'"),
+        )
+        .arg(
+            Arg::with_name("sslrootcert")
+                .long("sslrootcert")
+                .takes_value(true)
+                .help("a file of CA certificates to verify the server against, or 'system'"),
+        )
+        .arg(
+            Arg::with_name("sslcert")
+                .long("sslcert")
+                .takes_value(true)
+                .help("a client certificate to present to the server"),
+        )
+        .arg(
+            Arg::with_name("sslkey")
+                .long("sslkey")
+                .takes_value(true)
+                .help("the private key for --sslcert"),
         )
         .arg(
             Arg::with_name("schema")
@@ -19,6 +87,17 @@ pub fn new<'a>() -> App<'a, 'a> {
                 .default_value("public")
                 .help("comma-separated schemas from which to copy"),
         )
+        .arg(
+            Arg::with_name("schema_naming")
+                .long("schema-naming")
+                .takes_value(true)
+                .possible_values(NAMING_POLICIES)
+                .default_value("prefix")
+                .help(
+                    "how to keep relations from multiple schemas apart: rename them \
+                    `schema__relation`, or attach a database per schema",
+                ),
+        )
         .arg(
             Arg::with_name("DEST")
                 .long("dest")
@@ -26,6 +105,254 @@ pub fn new<'a>() -> App<'a, 'a> {
                 .required(true)
                 .help("a path to a sqlite3 file or 'STDOUT'"),
         )
+        .arg(
+            Arg::with_name("include_table")
+                .long("include-table")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(validate_pattern)
+                .help("only copy tables matching this glob or /regex/; repeatable"),
+        )
+        .arg(
+            Arg::with_name("exclude_table")
+                .long("exclude-table")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(validate_pattern)
+                .help("don't copy tables matching this glob or /regex/; repeatable"),
+        )
+        .arg(
+            Arg::with_name("include_view")
+                .long("include-view")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(validate_pattern)
+                .help("only copy views matching this glob or /regex/; repeatable"),
+        )
+        .arg(
+            Arg::with_name("exclude_view")
+                .long("exclude-view")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(validate_pattern)
+                .help("don't copy views matching this glob or /regex/; repeatable"),
+        )
+        .arg(
+            Arg::with_name("on_excluded_fk")
+                .long("on-excluded-fk")
+                .takes_value(true)
+                .possible_values(EXCLUDED_FK_POLICIES)
+                .default_value("fail")
+                .help(
+                    "what to do when a copied table has a foreign key to an excluded one: \
+                    drop the foreign key, include the referenced table, or fail",
+                ),
+        )
+        .arg(
+            Arg::with_name("on_untranslatable_check")
+                .long("on-untranslatable-check")
+                .takes_value(true)
+                .possible_values(UNTRANSLATABLE_POLICIES)
+                .default_value("comment")
+                .help(
+                    "what to do with a CHECK constraint SQLite can't evaluate: \
+                    keep postgres' definition as a comment, or drop it",
+                ),
+        )
+        .arg(
+            Arg::with_name("materialized_views")
+                .long("materialized-views")
+                .takes_value(true)
+                .possible_values(MATERIALIZED_VIEW_POLICIES)
+                .default_value("table")
+                .help(
+                    "copy materialized views as tables holding their current rows, \
+                    or recreate them as views",
+                ),
+        )
+        .arg(
+            Arg::with_name("partitions")
+                .long("partitions")
+                .takes_value(true)
+                .possible_values(PARTITION_POLICIES)
+                .default_value("merge")
+                .help(
+                    "copy each partitioned table as one table holding every partition's rows \
+                    (merge), or copy its leaf partitions as separate tables",
+                ),
+        )
+        .arg(
+            Arg::with_name("partition_column")
+                .long("partition-column")
+                .takes_value(true)
+                .value_name("NAME")
+                .help(
+                    "add a column NAME to merged partitioned tables, naming each row's partition; \
+                    only with `--partitions merge`",
+                ),
+        )
+        .arg(
+            Arg::with_name("inheritance")
+                .long("inheritance")
+                .takes_value(true)
+                .possible_values(INHERITANCE_POLICIES)
+                .default_value("only")
+                .help(
+                    "copy only each table's own rows, or flatten tables that use INHERITS \
+                    into their parents",
+                ),
+        )
+        .arg(
+            Arg::with_name("foreign_tables")
+                .long("foreign-tables")
+                .takes_value(true)
+                .possible_values(FOREIGN_TABLE_POLICIES)
+                .default_value("skip")
+                .help("skip foreign tables, or copy their current rows"),
+        )
+        .arg(
+            Arg::with_name("numeric")
+                .long("numeric")
+                .takes_value(true)
+                .possible_values(NUMERIC_POLICIES)
+                .default_value("text")
+                .help("how to store NUMERIC columns")
+                .long_help(
+                    "how to store NUMERIC columns: as exact decimal text, as REAL (lossy past \
+                    about 15 digits), as an INTEGER scaled by 10^scale (e.g. 1250 for 12.50 in \
+                    a NUMERIC(10,2)), or as a BLOB in postgres' binary format. Columns without \
+                    a declared scale, or with more than 18 digits, can't be scaled, and are \
+                    stored as text",
+                ),
+        )
+        .arg(
+            Arg::with_name("numeric_column")
+                .long("numeric-column")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("TABLE.COLUMN=POLICY")
+                .validator(validate_numeric_column)
+                .help("store one NUMERIC column differently than --numeric says")
+                .long_help(
+                    "store one NUMERIC column differently than --numeric says, e.g. \
+                    `ledger.entry.amount=integer`. TABLE is a pattern, like those of \
+                    --include-table",
+                ),
+        )
+        .arg(
+            Arg::with_name("datetime")
+                .long("datetime")
+                .takes_value(true)
+                .possible_values(DATETIME_POLICIES)
+                .default_value("text")
+                .help("how to store DATE, TIMESTAMP, and TIMESTAMPTZ columns")
+                .long_help(
+                    "how to store DATE, TIMESTAMP, and TIMESTAMPTZ columns: as ISO-8601 TEXT, \
+                    as INTEGER seconds (unix) or milliseconds (unix-ms) since 1970-01-01 UTC, or \
+                    as a REAL Julian day. Each is a format SQLite's date and time functions \
+                    read. TIMESTAMP is taken to be in UTC. TIME and TIMETZ are always TEXT",
+                ),
+        )
+        .arg(
+            Arg::with_name("timestamptz")
+                .long("timestamptz")
+                .takes_value(true)
+                .possible_values(TIMESTAMPTZ_POLICIES)
+                .default_value("utc")
+                .help("with --datetime text, write TIMESTAMPTZ in UTC or in --time-zone")
+                .long_help(
+                    "with --datetime text, write TIMESTAMPTZ values in UTC, e.g. \
+                    `2020-02-29T12:34:56+00:00`, or in --time-zone with its UTC offset, e.g. \
+                    `2020-02-29T18:04:56.000000+05:30`",
+                ),
+        )
+        .arg(
+            Arg::with_name("time_zone")
+                .long("time-zone")
+                .takes_value(true)
+                .value_name("ZONE")
+                .help("the time zone to read TIMESTAMPTZ values in, e.g. Europe/Paris")
+                .long_help(
+                    "the time zone of the postgres sessions that read SRC, e.g. Europe/Paris, \
+                    which --timestamptz offset writes TIMESTAMPTZ values in. Defaults to UTC",
+                ),
+        )
+        .arg(
+            Arg::with_name("arrays")
+                .long("arrays")
+                .takes_value(true)
+                .possible_values(ARRAY_POLICIES)
+                .default_value("json")
+                .help("store array columns as JSON, or as tables with a row per element")
+                .long_help(
+                    "store array columns as JSON text, e.g. `[[1,2],[3,null]]`, or move each \
+                    one to a table `<table>_<column>` with a row per element, keyed by the \
+                    table's primary key and the element's ordinal. Columns of tables without \
+                    a primary key stay JSON",
+                ),
+        )
+        .arg(
+            Arg::with_name("autoincrement")
+                .long("autoincrement")
+                .takes_value(false)
+                .help(
+                    "declare serial and identity primary keys AUTOINCREMENT, \
+                    so SQLite never reuses their ids",
+                ),
+        )
+        .arg(
+            Arg::with_name("jobs")
+                .long("jobs")
+                .short("j")
+                .takes_value(true)
+                .default_value("1")
+                .validator(validate_jobs)
+                .help("how many postgres connections to read tables with at once")
+                .long_help(
+                    "how many postgres connections to read tables with at once. Every \
+                    connection reads from the same snapshot, and rows are still written to \
+                    DEST one table at a time, in foreign key order",
+                ),
+        )
+        .arg(
+            Arg::with_name("extractor")
+                .long("extractor")
+                .takes_value(true)
+                .possible_values(EXTRACTORS)
+                .default_value("copy")
+                .help(
+                    "how to read rows: with a binary COPY, or with a SELECT (slower, for \
+                    comparison)",
+                ),
+        )
+        .arg(
+            Arg::with_name("pragma")
+                .long("pragma")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("NAME=VALUE")
+                .validator(validate_pragma)
+                .help("override one of the pragmas used while loading DEST")
+                .long_help(
+                    "override one of the pragmas used while loading DEST. The defaults are \
+                    page_size=8192, journal_mode=OFF, synchronous=OFF, cache_size=-262144, \
+                    temp_store=MEMORY, locking_mode=EXCLUSIVE, and mmap_size=268435456",
+                ),
+        )
+        .arg(
+            Arg::with_name("final_journal_mode")
+                .long("final-journal-mode")
+                .takes_value(true)
+                .possible_values(FINAL_JOURNAL_MODES)
+                .default_value("delete")
+                .help("the journal mode DEST is left in once it's loaded"),
+        )
         .arg(
             Arg::with_name("overwrite")
                 .long("overwrite")
@@ -57,7 +384,6 @@ pub fn new<'a>() -> App<'a, 'a> {
                 .help("don't produce any inserts"),
         )
         .group(ArgGroup::with_name("output_type").args(&["data_only", "schema_only"]));
-    // TODO: respoect PGHOST PGOPTIONS PGPORT PGUSER and listen for password
     return result;
 }
 // TODO: tests of cli-parsing
@@ -79,3 +405,165 @@ fn test_parsing_src_and_dest() {
     assert_eq!(src, "postgres://user:pw@dbhost.com:5432");
     assert_eq!(dst, "./my.db");
 }
+
+#[test]
+fn test_src_is_optional() {
+    let cli = new();
+    let args = vec!["pg-to-sqlite3", "--dest", "./my.db"];
+    let matches = cli.get_matches_from_safe(args).unwrap();
+    assert_eq!(matches.value_of("SRC"), None);
+}
+
+#[test]
+fn test_parsing_tls_options() {
+    let cli = new();
+    let args = vec![
+        "pg-to-sqlite3",
+        "--dest",
+        "./my.db",
+        "--sslmode",
+        "verify-full",
+        "--sslrootcert",
+        "./root.crt",
+    ];
+    let matches = cli.get_matches_from_safe(args).unwrap();
+    assert_eq!(matches.value_of("sslmode"), Some("verify-full"));
+    assert_eq!(matches.value_of("sslrootcert"), Some("./root.crt"));
+    let typo = vec!["pg-to-sqlite3", "--dest", "./my.db", "--sslmode", "verify"];
+    assert!(new().get_matches_from_safe(typo).is_err());
+}
+
+#[test]
+fn test_parsing_multiple_schemas() {
+    let cli = new();
+    let args = vec![
+        "pg-to-sqlite3",
+        "--dest",
+        "./my.db",
+        "--schema",
+        "public,audit",
+        "--schema-naming",
+        "attach",
+    ];
+    let matches = cli.get_matches_from_safe(args).unwrap();
+    assert_eq!(matches.value_of("schema"), Some("public,audit"));
+    assert_eq!(matches.value_of("schema_naming"), Some("attach"));
+}
+
+#[test]
+fn test_parsing_repeated_filters() {
+    let cli = new();
+    let args = vec![
+        "pg-to-sqlite3",
+        "--dest",
+        "./my.db",
+        "--exclude-table",
+        "audit_*",
+        "--exclude-table",
+        "/^stg_/",
+        "--on-excluded-fk",
+        "drop",
+    ];
+    let matches = cli.get_matches_from_safe(args).unwrap();
+    let excluded: Vec<&str> = matches.values_of("exclude_table").unwrap().collect();
+    assert_eq!(excluded, vec!["audit_*", "/^stg_/"]);
+    assert_eq!(matches.value_of("on_excluded_fk"), Some("drop"));
+    assert_eq!(matches.value_of("on_untranslatable_check"), Some("comment"));
+    let bad = vec![
+        "pg-to-sqlite3",
+        "--dest",
+        "./my.db",
+        "--include-view",
+        "/(/",
+    ];
+    assert!(new().get_matches_from_safe(bad).is_err());
+}
+
+#[test]
+fn test_parsing_jobs() {
+    let args = vec!["pg-to-sqlite3", "--dest", "./my.db", "-j", "4"];
+    let matches = new().get_matches_from_safe(args).unwrap();
+    assert_eq!(matches.value_of("jobs"), Some("4"));
+    let default = vec!["pg-to-sqlite3", "--dest", "./my.db"];
+    let matches = new().get_matches_from_safe(default).unwrap();
+    assert_eq!(matches.value_of("jobs"), Some("1"));
+    let zero = vec!["pg-to-sqlite3", "--dest", "./my.db", "--jobs", "0"];
+    assert!(new().get_matches_from_safe(zero).is_err());
+}
+
+#[test]
+fn test_parsing_pragmas() {
+    let args = vec![
+        "pg-to-sqlite3",
+        "--dest",
+        "./my.db",
+        "--pragma",
+        "synchronous=NORMAL",
+        "--pragma",
+        "cache_size=-2000",
+        "--final-journal-mode",
+        "wal",
+    ];
+    let matches = new().get_matches_from_safe(args).unwrap();
+    let pragmas: Vec<&str> = matches.values_of("pragma").unwrap().collect();
+    assert_eq!(pragmas, vec!["synchronous=NORMAL", "cache_size=-2000"]);
+    assert_eq!(matches.value_of("final_journal_mode"), Some("wal"));
+    let unknown = vec![
+        "pg-to-sqlite3",
+        "--dest",
+        "./my.db",
+        "--pragma",
+        "foreign_keys=ON",
+    ];
+    assert!(new().get_matches_from_safe(unknown).is_err());
+    let injected = vec![
+        "pg-to-sqlite3",
+        "--dest",
+        "./my.db",
+        "--pragma",
+        "cache_size=1; DROP",
+    ];
+    assert!(new().get_matches_from_safe(injected).is_err());
+}
+
+#[test]
+fn test_parsing_numeric_storage() {
+    let args = vec![
+        "pg-to-sqlite3",
+        "--dest",
+        "./my.db",
+        "--numeric",
+        "real",
+        "--numeric-column",
+        "ledger.*.amount=integer",
+    ];
+    let matches = new().get_matches_from_safe(args).unwrap();
+    assert_eq!(matches.value_of("numeric"), Some("real"));
+    let default = vec!["pg-to-sqlite3", "--dest", "./my.db"];
+    let matches = new().get_matches_from_safe(default).unwrap();
+    assert_eq!(matches.value_of("numeric"), Some("text"));
+    let no_column = vec![
+        "pg-to-sqlite3",
+        "--dest",
+        "./my.db",
+        "--numeric-column",
+        "amount=integer",
+    ];
+    assert!(new().get_matches_from_safe(no_column).is_err());
+}
+
+#[test]
+fn test_partition_column_conflicts_with_separate_partitions() {
+    let args = |extra: &[&'static str]| {
+        let mut args = vec!["pg-to-sqlite3", "--dest", "./my.db"];
+        args.extend(extra);
+        let matches = new().get_matches_from_safe(args)?;
+        return check(&matches).map(|_| matches);
+    };
+    let matches = args(&["--partition-column", "part"]).unwrap();
+    assert_eq!(matches.value_of("partitions"), Some("merge"));
+    assert_eq!(matches.value_of("partition_column"), Some("part"));
+    assert!(args(&["--partitions", "merge", "--partition-column", "part"]).is_ok());
+    let err = args(&["--partitions", "separate", "--partition-column", "part"]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::ArgumentConflict);
+}
@@ -0,0 +1,4 @@
+pub mod cli;
+pub mod pg;
+pub mod quote;
+pub mod sqlite;
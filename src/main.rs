@@ -1,35 +1,109 @@
-mod cli;
-mod pg;
-mod sqlite;
 use core::panic;
 use fallible_iterator::FallibleIterator;
-use std::{fs, path::Path};
+use pg_to_sqlite3::{cli, pg, sqlite};
+use std::{fs, path::Path, process};
 
 fn main() -> Result<(), pg::SqlError> {
     let args = cli::new().get_matches();
+    cli::check(&args).unwrap_or_else(|e| e.exit());
 
-    let src = args.value_of("SRC").unwrap(); // enforced by clap
-    let dest = args.value_of("DEST").unwrap();
-    let schema_name: &str = args.value_of("schema").unwrap();
+    let src = args.value_of("SRC");
+    let dest = args.value_of("DEST").unwrap(); // enforced by clap
+    let schemas: Vec<String> = args
+        .value_of("schema")
+        .unwrap()
+        .split(',')
+        .map(|s| s.trim().to_owned())
+        .filter(|s| !s.is_empty())
+        .collect();
+    let naming = pg::Naming::new(&schemas, args.value_of("schema_naming").unwrap());
+    let patterns = |name: &str| -> Vec<pg::Pattern> {
+        args.values_of(name)
+            .map(|values| values.map(|v| pg::Pattern::parse(v).unwrap()).collect()) // validated by clap
+            .unwrap_or_default()
+    };
+    let partitions = pg::Partitions::parse(args.value_of("partitions").unwrap());
+    let options = pg::SchemaOptions {
+        naming,
+        filter: pg::RelFilter {
+            include_tables: patterns("include_table"),
+            exclude_tables: patterns("exclude_table"),
+            include_views: patterns("include_view"),
+            exclude_views: patterns("exclude_view"),
+        },
+        on_excluded_fk: pg::ExcludedFk::parse(args.value_of("on_excluded_fk").unwrap()),
+        on_untranslatable_check: pg::Untranslatable::parse(
+            args.value_of("on_untranslatable_check").unwrap(),
+        ),
+        autoincrement: args.is_present("autoincrement"),
+        materialized_views: pg::MaterializedViews::parse(
+            args.value_of("materialized_views").unwrap(),
+        ),
+        partitions,
+        partition_column: args.value_of("partition_column").map(|c| c.to_owned()),
+        inheritance: pg::Inheritance::parse(args.value_of("inheritance").unwrap()),
+        foreign_tables: pg::ForeignTables::parse(args.value_of("foreign_tables").unwrap()),
+        numeric: pg::Numeric::parse(args.value_of("numeric").unwrap()),
+        numeric_columns: args
+            .values_of("numeric_column")
+            .map(|values| {
+                values
+                    .map(|v| pg::NumericColumn::parse(v).unwrap())
+                    .collect()
+            }) // validated by clap
+            .unwrap_or_default(),
+        datetime: pg::Datetime::parse(args.value_of("datetime").unwrap()),
+        timestamptz: pg::Timestamptz::parse(args.value_of("timestamptz").unwrap()),
+        arrays: pg::Arrays::parse(args.value_of("arrays").unwrap()),
+    };
     let overwrite = args.is_present("overwrite");
     let no_views = args.is_present("no_views");
     let schema_only = args.is_present("schema_only");
     let data_only = args.is_present("data_only");
+    let extractor = pg::Extractor::parse(args.value_of("extractor").unwrap());
+    let jobs: usize = args.value_of("jobs").unwrap().parse().unwrap(); // validated by clap
 
-    let mut conn = pg::connect(src);
-    let sch = pg::SchemaInformation::new(&mut conn, schema_name);
+    let mut overrides = pg::ConnParams::new();
+    for key in &["sslmode", "sslrootcert", "sslcert", "sslkey"] {
+        if let Some(value) = args.value_of(key) {
+            overrides.insert((*key).to_owned(), value.to_owned());
+        }
+    }
+    let time_zone = args.value_of("time_zone");
+    let connect = || {
+        let mut client = pg::connect(src, &overrides)?;
+        if let Some(zone) = time_zone {
+            pg::set_time_zone(&mut client, zone).map_err(pg::ConnectError::Postgres)?;
+        }
+        return Ok(client);
+    };
+    let mut client = connect()?;
+    let mut conn = pg::begin_snapshot(&mut client)?;
+    let sch = pg::SchemaInformation::new(&mut conn, &schemas, options);
 
     if dest == "stdout" || dest == "STDOUT" {
+        // there's no DEST to put attached databases beside
+        let scratch = std::env::temp_dir().join(format!("pg-to-sqlite3-{}.db", process::id()));
+        let attach = sch.attach_statements(&scratch.to_string_lossy());
+        if !attach.is_empty() {
+            println!("{}", attach);
+        }
         if data_only {
             println!("-- skipping table creation");
         } else {
             println!("{}", &sch.create_table_statements());
+            println!("{}", &sch.sequence_statements());
         }
         if no_views || data_only {
             println!("-- skipping view creation");
         } else {
             println!("{}", &sch.create_view_statements());
         }
+        if data_only {
+            println!("-- skipping index creation");
+        } else {
+            println!("{}", &sch.create_index_statements());
+        }
         return Ok(());
     } else {
         let dest_file = Path::new(dest);
@@ -50,7 +124,20 @@ fn main() -> Result<(), pg::SqlError> {
         }
     }
     // TODO: if the dest _file_ exists, require an --overwrite arg
-    let mut lite = rusqlite::Connection::open(dest).unwrap();
+    let staging = sqlite::Staging::new(dest, &sch.attachments(dest), data_only)?;
+    let mut lite = rusqlite::Connection::open(staging.path()).unwrap();
+    sqlite::attach_databases(&lite, staging.attachments())?;
+    let attached: Vec<String> = staging
+        .attachments()
+        .iter()
+        .map(|(schema, _)| schema.to_owned())
+        .collect();
+    let pragmas: Vec<(String, String)> = args
+        .values_of("pragma")
+        .map(|values| values.map(|v| sqlite::parse_pragma(v).unwrap()).collect()) // validated by clap
+        .unwrap_or_default();
+    let profile = sqlite::LoadProfile::new(&pragmas, args.value_of("final_journal_mode").unwrap());
+    profile.apply(&lite, &attached)?;
 
     if data_only {
         println!("-- skipping table creation");
@@ -72,13 +159,23 @@ fn main() -> Result<(), pg::SqlError> {
             false,
         )?;
         let mut txn = lite.transaction()?;
-        for table_name in sch.order {
-            match &sch.tables.get(&table_name) {
-                Some(tbl) => {
-                    println!("transferring {}", &table_name);
-                    pg::transfer_table_rows(&mut conn, &mut txn, tbl)?;
+        if jobs > 1 {
+            let snapshot = pg::export_snapshot(&mut conn)?;
+            let tables: Vec<&pg::Table> = sch
+                .order
+                .iter()
+                .filter_map(|name| sch.tables.get(name))
+                .collect();
+            pg::transfer_tables(jobs, connect, &snapshot, extractor, &mut txn, &tables)?;
+        } else {
+            for table_name in &sch.order {
+                match &sch.tables.get(table_name) {
+                    Some(tbl) => {
+                        println!("transferring {}", &table_name);
+                        pg::transfer_table_rows(&mut conn, &mut txn, tbl, extractor)?;
+                    }
+                    _ => {} // not a table
                 }
-                _ => {} // not a table
             }
         }
         println!("committing rows...");
@@ -88,9 +185,34 @@ fn main() -> Result<(), pg::SqlError> {
             rusqlite::config::DbConfig::SQLITE_DBCONFIG_ENABLE_FKEY,
             true,
         )?;
+        let violations = sqlite::check_foreign_keys(&lite, &sch.foreign_key_checks())?;
+        if !violations.is_empty() {
+            panic!(
+                "{} has rows that break foreign keys, so it was left untouched:\n  {}",
+                dest,
+                violations.join("\n  ")
+            );
+        }
     }
-
-    // now indices
+    if data_only {
+        println!("-- skipping index creation");
+    } else {
+        println!("creating indexes...");
+        sqlite::create_all_indexes(&mut lite, &sch.create_index_statements())?;
+    }
+    lite.execute_batch(&sch.sequence_statements())?;
+    profile.finish(&lite, &attached)?;
+    let problems = sqlite::check_integrity(&lite, &attached)?;
+    if !problems.is_empty() {
+        panic!(
+            "{} failed its integrity check, so it was left untouched:\n  {}",
+            dest,
+            problems.join("\n  ")
+        );
+    }
+    lite.close().map_err(|(_, e)| e)?;
+    staging.commit()?;
+    conn.commit()?;
     Ok(())
 
     // use petgraph::dot::Dot;
@@ -0,0 +1,223 @@
+//! Store array columns as JSON text, or move each one into a child table
+//! with a row per element.
+
+use super::{ColInfo, FkeyConstraint, Naming, PkeyConstraint, Table};
+use crate::quote;
+use postgres_types::{Kind, Type as PgType};
+use std::collections::HashMap;
+
+/// How to store an array column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arrays {
+    /// a JSON array, as postgres' `array_to_json` writes it, e.g.
+    /// `[[1,2],[3,null]]`
+    Json,
+    /// a child table `<table>_<column>`, keyed by the table's primary key and
+    /// each element's `ordinal`, holding the element as `value`
+    Table,
+}
+
+pub const ARRAY_POLICIES: &[&str] = &["json", "table"];
+
+/// The columns of a child table that aren't its parent's key.
+const ORDINAL: &str = "ordinal";
+const VALUE: &str = "value";
+/// What `from_elements` calls the unnested array.
+const ELEMENT: &str = "element";
+
+impl Arrays {
+    pub fn parse(policy: &str) -> Arrays {
+        match policy {
+            "json" => Arrays::Json,
+            "table" => Arrays::Table,
+            other => panic!("unknown array policy {:?}", other),
+        }
+    }
+}
+
+/// The type of `pg_type`'s elements, if it's an array.
+pub fn element_type(pg_type: &PgType) -> Option<&PgType> {
+    match pg_type.kind() {
+        Kind::Array(element) => return Some(element),
+        _ => return None,
+    }
+}
+
+/// What to select to read array column `column`, quoted, as JSON text.
+/// Arrays of any dimension nest, and NULL elements become `null`, but lower
+/// bounds other than 1 are lost.
+pub fn select_as_json(column: &str) -> String {
+    return format!("array_to_json({})", column);
+}
+
+/// What to select rows from to read the elements of `table`'s array column,
+/// if it's a child table: `relation` joined to each element, so `ordinal`
+/// and `value` can be selected beside the key. A multi-dimensional array's
+/// elements are numbered in storage order, last subscript fastest.
+pub fn from_elements(table: &Table, relation: &str) -> String {
+    return match &table.unnest {
+        Some(column) => format!(
+            "{}, unnest({}) WITH ORDINALITY AS {}({}, {})",
+            relation,
+            quote::pg(column),
+            ELEMENT,
+            VALUE,
+            ORDINAL
+        ),
+        None => relation.to_owned(),
+    };
+}
+
+/// `column` of `table`, quoted for postgres. A child table's `ordinal` and
+/// `value` are qualified, since its parent may have columns of those names.
+pub fn select_column(table: &Table, column: &str) -> String {
+    if table.unnest.is_some() && (column == ORDINAL || column == VALUE) {
+        return format!("{}.{}", ELEMENT, quote::pg(column));
+    }
+    return quote::pg(column);
+}
+
+/// An array column's default, as `expr::default_to_sqlite` translated it,
+/// as JSON. Only the empty array can be converted.
+pub fn default_to_sqlite(default: &str) -> Result<String, String> {
+    match default {
+        "'{}'" => return Ok("'[]'".to_owned()),
+        _ => return Err("only an empty array default can be written as JSON".to_owned()),
+    }
+}
+
+/// Move each array column of `table` into a child table, and return the
+/// children. A column stays in `table`, as JSON, if `table` has no primary
+/// key to key its elements by. Unique constraints on a moved column are
+/// dropped.
+pub fn split_columns(table: &mut Table, naming: &Naming) -> Vec<Table> {
+    let arrays: Vec<String> = table
+        .column_order
+        .iter()
+        .filter(|name| element_type(&table.columns[*name].data_type).is_some())
+        .cloned()
+        .collect();
+    let key = match table.pk_constraints.as_slice() {
+        [pk] => pk.columns.clone(),
+        _ => vec![],
+    };
+    let mut children = vec![];
+    for column in arrays {
+        let unkeyable = if key.is_empty() {
+            Some(format!("{} has no primary key", table.sqlite_name))
+        } else if key.contains(&column) {
+            Some("it's part of the primary key".to_owned())
+        } else if key.iter().any(|k| k == ORDINAL || k == VALUE) {
+            Some(format!(
+                "the primary key has a column named {} or {}",
+                ORDINAL, VALUE
+            ))
+        } else {
+            None
+        };
+        if let Some(reason) = unkeyable {
+            println!(
+                "-- storing {}.{} as JSON, not a table of its elements: {}",
+                table.sqlite_name, column, reason
+            );
+            continue;
+        }
+        let child = child_table(table, &key, &column, naming);
+        println!(
+            "-- moving the elements of {}.{} to {}",
+            table.sqlite_name, column, child.sqlite_name
+        );
+        let sqlite_name = &table.sqlite_name;
+        table.unique_constraints.retain(|uq| {
+            if !uq.columns.contains(&column) {
+                return true;
+            }
+            println!(
+                "-- dropping unique constraint {} on {}: its column {} moved to {}",
+                uq.name, sqlite_name, column, child.sqlite_name
+            );
+            return false;
+        });
+        table.column_order.retain(|name| name != &column);
+        table.columns.remove(&column);
+        children.push(child);
+    }
+    return children;
+}
+
+/// The child table `<table>_<column>`, whose rows are the elements of
+/// `parent`'s array column `column`, with a foreign key back to `parent`.
+fn child_table(parent: &Table, key: &[String], column: &str, naming: &Naming) -> Table {
+    let name = format!("{}_{}", parent.name, column);
+    let sqlite_name = naming.sqlite_name(&parent.schema, &name);
+    let element = element_type(&parent.columns[column].data_type).unwrap();
+    let mut columns = HashMap::new();
+    for name in key {
+        let col = &parent.columns[name];
+        columns.insert(
+            name.to_owned(),
+            ColInfo::new(name, col.data_type.clone(), false),
+        );
+    }
+    columns.insert(
+        ORDINAL.to_owned(),
+        ColInfo::new(ORDINAL, PgType::INT8, false),
+    );
+    columns.insert(VALUE.to_owned(), ColInfo::new(VALUE, element.clone(), true));
+    let mut column_order = key.to_vec();
+    column_order.push(ORDINAL.to_owned());
+    column_order.push(VALUE.to_owned());
+    let mut pk_columns = key.to_vec();
+    pk_columns.push(ORDINAL.to_owned());
+    let pk = PkeyConstraint {
+        name: format!("{}_pkey", name),
+        table: sqlite_name.to_owned(),
+        columns: pk_columns,
+    };
+    let fk = FkeyConstraint {
+        name: format!("{}_{}_fkey", name, key.join("_")),
+        schema: parent.schema.to_owned(),
+        table: sqlite_name.to_owned(),
+        columns: key.to_vec(),
+        foreign_schema: parent.schema.to_owned(),
+        foreign_table: parent.sqlite_name.to_owned(),
+        foreign_columns: key.to_vec(),
+        references: quote::sqlite(&naming.local_name(&parent.schema, &parent.name)),
+        inherited: false,
+        // the elements are part of the row they came from
+        on_delete: Some("CASCADE".to_owned()),
+        on_update: Some("CASCADE".to_owned()),
+        match_type: None,
+        deferrable: false,
+        initially_deferred: false,
+    };
+    return Table {
+        oid: parent.oid,
+        schema: parent.schema.to_owned(),
+        name: parent.name.to_owned(), // the relation to read
+        sqlite_ident: naming.sqlite_ident(&parent.schema, &name),
+        sqlite_name,
+        column_order,
+        columns,
+        pk_constraints: vec![pk],
+        fkey_constraints: vec![fk],
+        unique_constraints: vec![],
+        check_constraints: vec![],
+        indexes: vec![],
+        approx_n_rows: parent.approx_n_rows,
+        materialized: false,
+        partition_column: None,
+        include_children: parent.include_children,
+        unnest: Some(column.to_owned()),
+    };
+}
+
+#[test]
+fn test_finding_element_types() {
+    assert_eq!(element_type(&PgType::TEXT_ARRAY), Some(&PgType::TEXT));
+    assert_eq!(element_type(&PgType::TIMETZ_ARRAY), Some(&PgType::TIMETZ));
+    assert_eq!(element_type(&PgType::INT2_VECTOR), Some(&PgType::INT2));
+    assert_eq!(element_type(&PgType::TEXT), None);
+    assert_eq!(default_to_sqlite("'{}'"), Ok("'[]'".to_owned()));
+    assert!(default_to_sqlite("'{a,b}'").is_err());
+}
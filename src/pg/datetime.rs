@@ -0,0 +1,478 @@
+//! Store DATE, TIMESTAMP, and TIMESTAMPTZ columns as ISO-8601 text, Unix
+//! time, or Julian days: the three representations SQLite's date and time
+//! functions understand.
+
+use chrono::{DateTime, FixedOffset, Utc};
+use postgres_types::{FromSql as FromPgSql, Type as PgType};
+use rusqlite::types::{Type as SqliteType, ValueRef};
+use std::fmt;
+
+/// How to store a date or timestamp column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Datetime {
+    /// ISO-8601 text, e.g. `2020-02-29T12:34:56.500`; TIMESTAMPTZ in UTC
+    Text,
+    /// TIMESTAMPTZ as ISO-8601 text in the postgres session's time zone
+    /// (see `--time-zone`), e.g. `2020-02-29T18:04:56.500000+05:30`. Only
+    /// chosen for TIMESTAMPTZ columns, with `--timestamptz offset`.
+    TextWithOffset,
+    /// whole seconds since 1970-01-01 00:00:00 UTC
+    Unix,
+    /// milliseconds since 1970-01-01 00:00:00 UTC
+    UnixMillis,
+    /// fractional days since noon in Greenwich on November 24, 4714 B.C.
+    Julian,
+}
+
+pub const DATETIME_POLICIES: &[&str] = &["text", "unix", "unix-ms", "julian"];
+
+/// The Julian day of 1970-01-01 00:00:00 UTC.
+const UNIX_EPOCH_JULIAN_DAY: f64 = 2440587.5;
+
+impl Datetime {
+    pub fn parse(policy: &str) -> Datetime {
+        match policy {
+            "text" => Datetime::Text,
+            "unix" => Datetime::Unix,
+            "unix-ms" => Datetime::UnixMillis,
+            "julian" => Datetime::Julian,
+            other => panic!("unknown date/time storage policy {:?}", other),
+        }
+    }
+
+    pub fn sqlite_type(&self) -> SqliteType {
+        match self {
+            Datetime::Text | Datetime::TextWithOffset => SqliteType::Text,
+            Datetime::Unix | Datetime::UnixMillis => SqliteType::Integer,
+            Datetime::Julian => SqliteType::Real,
+        }
+    }
+
+    /// How SQLite should read the stored values, for the column's comment.
+    pub fn describe(&self) -> Option<&'static str> {
+        match self {
+            Datetime::Text | Datetime::TextWithOffset => return None,
+            Datetime::Unix => return Some("Unix seconds"),
+            Datetime::UnixMillis => return Some("Unix milliseconds"),
+            Datetime::Julian => return Some("a Julian day"),
+        }
+    }
+}
+
+/// How to write TIMESTAMPTZ values as text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timestamptz {
+    /// in UTC, e.g. `2020-02-29T12:34:56.500+00:00`
+    Utc,
+    /// in the postgres session's time zone, with its UTC offset
+    Offset,
+}
+
+pub const TIMESTAMPTZ_POLICIES: &[&str] = &["utc", "offset"];
+
+impl Timestamptz {
+    pub fn parse(policy: &str) -> Timestamptz {
+        match policy {
+            "utc" => Timestamptz::Utc,
+            "offset" => Timestamptz::Offset,
+            other => panic!("unknown timestamptz policy {:?}", other),
+        }
+    }
+}
+
+/// How to store a column of type `pg_type`, if it's a date or timestamp.
+/// TIME and TIMETZ are times of day rather than points in time, and are
+/// always stored as text.
+pub fn choose(pg_type: &PgType, storage: Datetime, timestamptz: Timestamptz) -> Option<Datetime> {
+    match pg_type {
+        &PgType::DATE | &PgType::TIMESTAMP => return Some(storage),
+        &PgType::TIMESTAMPTZ => match (storage, timestamptz) {
+            (Datetime::Text, Timestamptz::Offset) => return Some(Datetime::TextWithOffset),
+            _ => return Some(storage),
+        },
+        _ => return None,
+    }
+}
+
+/// What to select to read `column`, quoted, when it's stored as
+/// `Datetime::TextWithOffset`: the text, formatted by postgres, which knows
+/// the session's time zone, or else `infinity` or `-infinity`.
+pub fn select_with_offset(column: &str) -> String {
+    return format!(
+        "CASE WHEN isfinite({0}) THEN to_char({0}, 'YYYY-MM-DD\"T\"HH24:MI:SS.USTZH:TZM') \
+        ELSE {0}::text END",
+        column
+    );
+}
+
+/// A DATE, TIMESTAMP, or TIMESTAMPTZ, or `None` for `infinity` and
+/// `-infinity`, which postgres stores as the largest and smallest values of
+/// its binary format, and chrono has no equivalent for.
+pub struct Finite<T>(pub Option<T>);
+
+impl<'a, T: FromPgSql<'a>> FromPgSql<'a> for Finite<T> {
+    fn from_sql(
+        pg_type: &PgType,
+        raw: &'a [u8],
+    ) -> Result<Finite<T>, Box<dyn std::error::Error + Sync + Send>> {
+        let infinite = match raw.len() {
+            4 => {
+                let mut days = [0; 4];
+                days.copy_from_slice(raw);
+                let days = i32::from_be_bytes(days);
+                days == i32::MAX || days == i32::MIN
+            }
+            8 => {
+                let mut micros = [0; 8];
+                micros.copy_from_slice(raw);
+                let micros = i64::from_be_bytes(micros);
+                micros == i64::MAX || micros == i64::MIN
+            }
+            _ => false,
+        };
+        if infinite {
+            return Ok(Finite(None));
+        }
+        return Ok(Finite(Some(T::from_sql(pg_type, raw)?)));
+    }
+
+    fn accepts(pg_type: &PgType) -> bool {
+        return T::accepts(pg_type);
+    }
+}
+
+/// The point in time `instant`, stored as `storage` asks.
+pub fn instant(storage: Datetime, instant: DateTime<Utc>) -> ValueRef<'static> {
+    match storage {
+        Datetime::Unix => return ValueRef::Integer(instant.timestamp()),
+        Datetime::UnixMillis => return ValueRef::Integer(instant.timestamp_millis()),
+        Datetime::Julian => {
+            let seconds = instant.timestamp();
+            let days = seconds.div_euclid(86_400) as f64;
+            let fraction = (seconds.rem_euclid(86_400) as f64
+                + instant.timestamp_subsec_nanos() as f64 / 1e9)
+                / 86_400.0;
+            return ValueRef::Real(UNIX_EPOCH_JULIAN_DAY + days + fraction);
+        }
+        Datetime::Text | Datetime::TextWithOffset => {
+            panic!("{:?} isn't a number", storage)
+        }
+    }
+}
+
+/// `literal`, quoted, with a UTC offset SQLite can read: postgres prints
+/// whole hours as `+05` rather than `+05:00`. Only a time has an offset, so
+/// a date's `-01` is left alone.
+fn with_full_offset(literal: &str) -> String {
+    let bytes = literal.as_bytes();
+    let n = bytes.len();
+    let hours_only = n > 5
+        && matches!(bytes[n - 4], b'+' | b'-')
+        && bytes[n - 3..n - 1].iter().all(|b| b.is_ascii_digit())
+        && bytes[n - 5].is_ascii_digit()
+        && literal[..n - 4].contains(':');
+    if hours_only {
+        return format!("{}:00'", &literal[..n - 1]);
+    }
+    return literal.to_owned();
+}
+
+/// An error unless SQLite's date functions can read `value`, which is NULL
+/// in SQLite if `converted` is, like `'infinity'`.
+fn check_readable(value: &str, converted: &str) -> Result<(), String> {
+    let conn = rusqlite::Connection::open_in_memory().map_err(|e| e.to_string())?;
+    let unreadable: bool = conn
+        .query_row(
+            &format!("SELECT {} IS NULL AND {} IS NOT NULL", converted, value),
+            rusqlite::NO_PARAMS,
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if unreadable {
+        return Err(format!("SQLite can't read {} as a date", value));
+    }
+    return Ok(());
+}
+
+/// A date or timestamp column's default, as `expr::default_to_sqlite`
+/// translated it, converted to the format the column's values are copied in.
+/// Text defaults are always in UTC, even with `--timestamptz offset`.
+pub fn default_to_sqlite(
+    default: &str,
+    pg_type: &PgType,
+    storage: Datetime,
+) -> Result<String, String> {
+    if default == "NULL" {
+        return Ok(default.to_owned());
+    }
+    let value = with_full_offset(default);
+    // dates are copied as midnight
+    let value = match pg_type {
+        &PgType::DATE => format!("date({})", value),
+        _ => value,
+    };
+    let converted = match (storage, pg_type) {
+        (Datetime::Text, &PgType::DATE) => format!("({})", value),
+        // as many fractional digits as chrono prints for a whole millisecond
+        (Datetime::Text, _) => {
+            let zone = match pg_type {
+                &PgType::TIMESTAMPTZ => " || '+00:00'",
+                _ => "",
+            };
+            format!(
+                "(strftime('%Y-%m-%dT%H:%M:%S', {0}) \
+                || replace(substr(strftime('%f', {0}), 3), '.000', ''){1})",
+                value, zone
+            )
+        }
+        (Datetime::TextWithOffset, _) => {
+            format!("(strftime('%Y-%m-%dT%H:%M:%f', {}) || '000+00:00')", value)
+        }
+        (Datetime::Unix, _) => format!("(CAST(strftime('%s', {}) AS INTEGER))", value),
+        (Datetime::UnixMillis, _) => format!(
+            "(CAST(round((julianday({}) - {}) * 86400000) AS INTEGER))",
+            value, UNIX_EPOCH_JULIAN_DAY
+        ),
+        (Datetime::Julian, _) => format!("(julianday({}))", value),
+    };
+    check_readable(default, &converted)?;
+    match storage {
+        // a constant reads better as the text it comes to
+        Datetime::Text | Datetime::TextWithOffset if default.starts_with('\'') => {
+            return evaluate(&converted);
+        }
+        _ => return Ok(converted),
+    }
+}
+
+/// `expr`, a constant, evaluated by SQLite and quoted as a literal.
+fn evaluate(expr: &str) -> Result<String, String> {
+    let conn = rusqlite::Connection::open_in_memory().map_err(|e| e.to_string())?;
+    return conn
+        .query_row(
+            &format!("SELECT quote({})", expr),
+            rusqlite::NO_PARAMS,
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string());
+}
+
+/// How a CHECK constraint or an index reads a date or timestamp `column`,
+/// quoted, stored as `storage`: as a Julian day, which compares the same in
+/// every storage, and like `literal_to_sqlite`'s literals.
+pub fn column_to_sqlite(column: &str, storage: Datetime) -> String {
+    match storage {
+        Datetime::Text | Datetime::TextWithOffset => return format!("julianday({})", column),
+        Datetime::Unix => return format!("julianday({}, 'unixepoch')", column),
+        Datetime::UnixMillis => return format!("julianday({} / 1000.0, 'unixepoch')", column),
+        Datetime::Julian => return column.to_owned(),
+    }
+}
+
+/// A quoted `literal` that postgres casts to `type_name`, as a Julian day
+/// if it's a date or timestamp, to compare with `column_to_sqlite`'s columns.
+pub fn literal_to_sqlite(literal: &str, type_name: &str) -> Result<Option<String>, String> {
+    if !["date", "timestamp", "timestamptz"].contains(&type_name) {
+        return Ok(None);
+    }
+    let converted = format!("julianday({})", with_full_offset(literal));
+    check_readable(literal, &converted)?;
+    return Ok(Some(converted));
+}
+
+/// A TIMETZ in postgres' binary format: microseconds since midnight, then
+/// the zone's offset in seconds west of UTC.
+pub struct PgTimetz {
+    micros: i64,
+    offset: FixedOffset,
+}
+
+impl<'a> FromPgSql<'a> for PgTimetz {
+    fn from_sql(
+        _: &PgType,
+        raw: &'a [u8],
+    ) -> Result<PgTimetz, Box<dyn std::error::Error + Sync + Send>> {
+        if raw.len() != 12 {
+            return Err(format!("a TIMETZ is 12 bytes, not {}", raw.len()).into());
+        }
+        let mut micros = [0; 8];
+        micros.copy_from_slice(&raw[..8]);
+        let mut west = [0; 4];
+        west.copy_from_slice(&raw[8..]);
+        let offset = FixedOffset::east_opt(-i32::from_be_bytes(west))
+            .ok_or("a TIMETZ's offset must be under a day")?;
+        return Ok(PgTimetz {
+            micros: i64::from_be_bytes(micros),
+            offset,
+        });
+    }
+
+    fn accepts(pg_type: &PgType) -> bool {
+        return pg_type == &PgType::TIMETZ;
+    }
+}
+
+/// Like a TIME, with the offset SQLite's time functions expect, e.g.
+/// `12:34:56.500-03:30`. postgres allows `24:00:00`, which is kept.
+impl fmt::Display for PgTimetz {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let seconds = self.micros / 1_000_000;
+        let micros = self.micros % 1_000_000;
+        write!(
+            f,
+            "{:02}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )?;
+        // as many fractional digits as chrono prints for a TIME
+        if micros % 1000 != 0 {
+            write!(f, ".{:06}", micros)?;
+        } else if micros != 0 {
+            write!(f, ".{:03}", micros / 1000)?;
+        }
+        let east = self.offset.local_minus_utc();
+        let sign = if east < 0 { '-' } else { '+' };
+        return write!(
+            f,
+            "{}{:02}:{:02}",
+            sign,
+            east.abs() / 3600,
+            east.abs() / 60 % 60
+        );
+    }
+}
+
+#[test]
+fn test_storing_instants() {
+    use chrono::TimeZone;
+    let leap_day = Utc.with_ymd_and_hms(2020, 2, 29, 12, 0, 0).unwrap();
+    assert_eq!(
+        instant(Datetime::Unix, leap_day),
+        ValueRef::Integer(1582977600)
+    );
+    assert_eq!(
+        instant(
+            Datetime::UnixMillis,
+            leap_day + chrono::Duration::milliseconds(500)
+        ),
+        ValueRef::Integer(1582977600500)
+    );
+    assert_eq!(
+        instant(Datetime::Julian, leap_day),
+        ValueRef::Real(2458909.0)
+    );
+    // before 1970, seconds round down rather than toward zero
+    let before = Utc.timestamp_opt(-1, 500_000_000).unwrap();
+    assert_eq!(instant(Datetime::Unix, before), ValueRef::Integer(-1));
+    assert_eq!(
+        instant(Datetime::UnixMillis, before),
+        ValueRef::Integer(-500)
+    );
+}
+
+#[test]
+fn test_converting_datetime_defaults() {
+    let conn = rusqlite::Connection::open_in_memory().unwrap();
+    let evaluate = |default: &str| -> rusqlite::types::Value {
+        return conn
+            .query_row(&format!("SELECT {}", default), rusqlite::NO_PARAMS, |row| {
+                row.get(0)
+            })
+            .unwrap();
+    };
+    let convert = |default: &str, pg_type: &PgType, storage: Datetime| {
+        return evaluate(&default_to_sqlite(default, pg_type, storage).unwrap());
+    };
+    use rusqlite::types::Value;
+    let leap_day = "'2020-02-29 12:00:00'";
+    let timestamp = &PgType::TIMESTAMP;
+    assert_eq!(
+        convert(leap_day, timestamp, Datetime::Unix),
+        Value::Integer(1582977600)
+    );
+    assert_eq!(
+        convert(leap_day, timestamp, Datetime::UnixMillis),
+        Value::Integer(1582977600000)
+    );
+    assert_eq!(
+        convert(leap_day, timestamp, Datetime::Julian),
+        Value::Real(2458909.0)
+    );
+    assert_eq!(
+        convert(leap_day, &PgType::DATE, Datetime::Unix),
+        Value::Integer(1582934400)
+    );
+    // text the way rows are copied
+    let text = |value: &str| Value::Text(value.to_owned());
+    assert_eq!(
+        convert(
+            "'2020-02-29 12:00:00.5+05'",
+            &PgType::TIMESTAMPTZ,
+            Datetime::Text
+        ),
+        text("2020-02-29T07:00:00.500+00:00")
+    );
+    assert_eq!(
+        convert(leap_day, timestamp, Datetime::Text),
+        text("2020-02-29T12:00:00")
+    );
+    assert_eq!(
+        convert(leap_day, &PgType::TIMESTAMPTZ, Datetime::TextWithOffset),
+        text("2020-02-29T12:00:00.000000+00:00")
+    );
+    assert_eq!(
+        convert(leap_day, &PgType::DATE, Datetime::Text),
+        text("2020-02-29")
+    );
+    assert_eq!(
+        default_to_sqlite(leap_day, timestamp, Datetime::Text),
+        Ok("'2020-02-29T12:00:00'".to_owned())
+    );
+    assert!(
+        default_to_sqlite("CURRENT_TIMESTAMP", timestamp, Datetime::Text)
+            .unwrap()
+            .starts_with("(strftime(")
+    );
+    assert!(default_to_sqlite("CURRENT_TIMESTAMP", timestamp, Datetime::Julian).is_ok());
+    assert!(default_to_sqlite("'infinity'", timestamp, Datetime::Unix).is_err());
+    assert!(default_to_sqlite("'infinity'", timestamp, Datetime::Text).is_err());
+    assert_eq!(
+        default_to_sqlite("NULL", timestamp, Datetime::Unix),
+        Ok("NULL".to_owned())
+    );
+}
+
+#[test]
+fn test_comparing_datetimes() {
+    assert_eq!(
+        literal_to_sqlite("'2000-01-01 00:00:00+00'", "timestamp"),
+        Ok(Some("julianday('2000-01-01 00:00:00+00:00')".to_owned()))
+    );
+    assert_eq!(
+        literal_to_sqlite("'2000-01-01'", "date"),
+        Ok(Some("julianday('2000-01-01')".to_owned()))
+    );
+    assert_eq!(literal_to_sqlite("'12:00'", "time"), Ok(None));
+    assert!(literal_to_sqlite("'-infinity'", "date").is_err());
+    assert_eq!(
+        column_to_sqlite("at", Datetime::UnixMillis),
+        "julianday(at / 1000.0, 'unixepoch')"
+    );
+}
+
+#[test]
+fn test_printing_timetz() {
+    let timetz = |micros: i64, west: i32| -> String {
+        let mut raw = micros.to_be_bytes().to_vec();
+        raw.extend_from_slice(&west.to_be_bytes());
+        return PgTimetz::from_sql(&PgType::TIMETZ, &raw)
+            .unwrap()
+            .to_string();
+    };
+    assert_eq!(timetz(45_296_500_000, 12_600), "12:34:56.500-03:30");
+    assert_eq!(timetz(45_296_000_001, -3600), "12:34:56.000001+01:00");
+    assert_eq!(timetz(0, 0), "00:00:00+00:00");
+    assert_eq!(timetz(86_400_000_000, 0), "24:00:00+00:00");
+    assert!(PgTimetz::from_sql(&PgType::TIMETZ, &[0; 8]).is_err());
+}
@@ -0,0 +1,335 @@
+//! Build SQLite DDL for the tables and indexes copied from postgres.
+//!
+//! Every definition in a `CREATE TABLE` gets its own line, and comments only
+//! ever end a line, so a comment can't swallow the SQL after it. Line breaks
+//! in a comment, say from a name or a default, become spaces, so nothing in
+//! it can end up outside it. Names are quoted wherever SQLite would misread
+//! them bare.
+
+use super::array;
+use super::object_types::sqlite_type_from_pg_type;
+use super::{CheckConstraint, ColInfo, FkeyConstraint, Index, Numeric, Table};
+use crate::quote;
+
+/// A `CREATE TABLE` statement, put together one definition at a time.
+/// https://www.sqlite.org/lang_createtable.html
+pub struct CreateTable {
+    name: String,
+    preamble: Vec<String>, // comment lines before the statement
+    definitions: Vec<Definition>,
+    trailer: Option<String>, // a comment after the statement
+}
+
+struct Definition {
+    sql: Option<String>, // `None` for a line that's only a comment
+    comment: Option<String>,
+}
+
+/// `comment`, on one line.
+fn one_line(comment: &str) -> String {
+    return comment.replace(['\n', '\r'], " ");
+}
+
+impl CreateTable {
+    pub fn new(name: &str) -> CreateTable {
+        return CreateTable {
+            name: name.to_owned(),
+            preamble: vec![],
+            definitions: vec![],
+            trailer: None,
+        };
+    }
+
+    /// A comment line before the statement.
+    pub fn preamble(&mut self, comment: &str) -> &mut CreateTable {
+        self.preamble.push(one_line(comment));
+        return self;
+    }
+
+    /// A column or table constraint, with an optional comment after it.
+    pub fn definition(&mut self, sql: &str, comment: Option<&str>) -> &mut CreateTable {
+        self.definitions.push(Definition {
+            sql: Some(sql.to_owned()),
+            comment: comment.map(one_line),
+        });
+        return self;
+    }
+
+    /// A line among the definitions that's only a comment, e.g. a
+    /// constraint SQLite can't evaluate.
+    pub fn comment(&mut self, comment: &str) -> &mut CreateTable {
+        self.definitions.push(Definition {
+            sql: None,
+            comment: Some(one_line(comment)),
+        });
+        return self;
+    }
+
+    /// A comment after the statement's closing `;`.
+    pub fn trailer(&mut self, comment: &str) -> &mut CreateTable {
+        self.trailer = Some(one_line(comment));
+        return self;
+    }
+
+    pub fn build(&self) -> String {
+        let mut ddl = String::new();
+        for comment in &self.preamble {
+            ddl.push_str(&format!("-- {}\n", comment));
+        }
+        ddl.push_str(&format!("CREATE TABLE {} (\n", self.name));
+        let mut first = true;
+        for definition in &self.definitions {
+            let mut line = match &definition.sql {
+                Some(sql) if first => format!("  {}", sql),
+                Some(sql) => format!("  , {}", sql),
+                None => "  ".to_owned(),
+            };
+            first = first && definition.sql.is_none();
+            if let Some(comment) = &definition.comment {
+                if definition.sql.is_some() {
+                    line.push(' ');
+                }
+                line.push_str(&format!("-- {}", comment));
+            }
+            ddl.push_str(&line);
+            ddl.push('\n');
+        }
+        ddl.push_str(");");
+        if let Some(comment) = &self.trailer {
+            ddl.push_str(&format!(" -- {}", comment));
+        }
+        ddl.push('\n');
+        return ddl;
+    }
+}
+
+/// `table`'s `CREATE TABLE` statement. A rowid alias is declared `PRIMARY
+/// KEY` on the column itself, as SQLite requires; other primary keys become
+/// table constraints, so they don't turn into rowid aliases.
+pub fn create_table(table: &Table) -> String {
+    let mut ddl = CreateTable::new(&table.sqlite_ident);
+    if table.materialized {
+        ddl.preamble(&format!(
+            "the rows of materialized view {}.{}",
+            table.schema, table.name
+        ));
+    }
+    if let Some(column) = &table.unnest {
+        ddl.preamble(&format!(
+            "the elements of {}.{}.{}, one per row",
+            table.schema, table.name, column
+        ));
+    }
+    for name in &table.column_order {
+        let (sql, comment) = column(&table.columns[name]);
+        ddl.definition(&sql, Some(&comment));
+    }
+    for pk in &table.pk_constraints {
+        ddl.definition(
+            &format!(
+                "CONSTRAINT {} PRIMARY KEY ({})",
+                quote::sqlite(&pk.name),
+                columns(&pk.columns)
+            ),
+            None,
+        );
+    }
+    for uq in &table.unique_constraints {
+        ddl.definition(
+            &format!(
+                "CONSTRAINT {} UNIQUE ({})",
+                quote::sqlite(&uq.name),
+                columns(&uq.columns)
+            ),
+            None,
+        );
+    }
+    for fk in &table.fkey_constraints {
+        ddl.definition(&foreign_key(fk), None);
+    }
+    for check in &table.check_constraints {
+        match &check.untranslatable {
+            None => ddl.definition(&check_constraint(check), None),
+            Some(reason) => ddl.comment(&format!(
+                "CONSTRAINT {} {} -- {}",
+                quote::sqlite(&check.name),
+                check.defn,
+                reason
+            )),
+        };
+    }
+    ddl.trailer(&format!("~ {} rows", table.approx_n_rows));
+    return ddl.build();
+}
+
+/// A column definition, and a comment with what postgres declared that
+/// SQLite doesn't: the column's postgres type and any untranslated default.
+fn column(col: &ColInfo) -> (String, String) {
+    let sqlite_type = match (col.numeric, col.datetime) {
+        (Some(storage), _) => storage.sqlite_type(),
+        (None, Some(storage)) => storage.sqlite_type(),
+        (None, None) => sqlite_type_from_pg_type(&col.data_type).unwrap(),
+    };
+    let sqlite_type = sqlite_type.to_string().to_ascii_uppercase();
+    let mut sql = format!("{} {}", quote::sqlite(&col.name), sqlite_type);
+    if col.rowid_alias {
+        sql.push_str(" PRIMARY KEY");
+    }
+    if col.autoincrement {
+        sql.push_str(" AUTOINCREMENT");
+    }
+    if !col.nullable {
+        sql.push_str(" NOT NULL");
+    }
+    if let Some(default) = &col.sqlite_default {
+        sql.push_str(&format!(" DEFAULT {}", default));
+    }
+    let mut comment = match array::element_type(&col.data_type) {
+        Some(element) => format!("{}[] as JSON", element.to_string().to_ascii_uppercase()),
+        None => col.data_type.to_string().to_ascii_uppercase(),
+    };
+    if let (Some(precision), Some(scale)) = (col.precision, col.scale) {
+        comment.push_str(&format!("({},{})", precision, scale));
+    }
+    if let (Some(Numeric::Integer), Some(scale)) = (col.numeric, col.scale) {
+        comment.push_str(&format!(" times 10^{}", scale));
+    }
+    if let Some(unit) = col.datetime.and_then(|storage| storage.describe()) {
+        comment.push_str(&format!(" as {}", unit));
+    }
+    if let (Some(default), None) = (&col.default, &col.sqlite_default) {
+        if !col.rowid_alias {
+            comment.push_str(&format!(" DEFAULT {}", default));
+        }
+    }
+    return (sql, comment);
+}
+
+/// A list of column names, e.g. a key's.
+fn columns(names: &[String]) -> String {
+    let quoted: Vec<String> = names.iter().map(|name| quote::sqlite(name)).collect();
+    return quoted.join(", ");
+}
+
+/// https://www.sqlite.org/syntax/foreign-key-clause.html
+fn foreign_key(fk: &FkeyConstraint) -> String {
+    let mut sql = format!(
+        "CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {}({})",
+        quote::sqlite(&fk.name),
+        columns(&fk.columns),
+        fk.references,
+        columns(&fk.foreign_columns)
+    );
+    // SQLite parses MATCH but always behaves as MATCH SIMPLE
+    if let Some(match_type) = &fk.match_type {
+        sql.push_str(&format!(" MATCH {}", match_type));
+    }
+    if let Some(action) = &fk.on_delete {
+        sql.push_str(&format!(" ON DELETE {}", action));
+    }
+    if let Some(action) = &fk.on_update {
+        sql.push_str(&format!(" ON UPDATE {}", action));
+    }
+    match (fk.deferrable, fk.initially_deferred) {
+        (true, true) => sql.push_str(" DEFERRABLE INITIALLY DEFERRED"),
+        (true, false) => sql.push_str(" DEFERRABLE INITIALLY IMMEDIATE"),
+        (false, _) => {}
+    }
+    return sql;
+}
+
+fn check_constraint(check: &CheckConstraint) -> String {
+    return format!(
+        "CONSTRAINT {} CHECK ({})",
+        quote::sqlite(&check.name),
+        check.expr
+    );
+}
+
+/// https://www.sqlite.org/lang_createindex.html
+pub fn create_index(index: &Index) -> String {
+    let unique = if index.unique { "UNIQUE " } else { "" };
+    let mut sql = format!(
+        "CREATE {}INDEX {} ON {} ({})",
+        unique,
+        index.ident,
+        index.local_table,
+        index.sqlite_keys().join(", ")
+    );
+    if let Some(predicate) = &index.predicate {
+        sql.push_str(&format!(" WHERE {}", predicate));
+    }
+    sql.push(';');
+    return sql;
+}
+
+#[test]
+fn test_building_create_table() {
+    let mut ddl = CreateTable::new("t");
+    ddl.preamble("a snapshot")
+        .comment("dropped: x")
+        .definition("id INTEGER PRIMARY KEY NOT NULL", Some("INT4"))
+        .definition("name TEXT NOT NULL", Some("TEXT DEFAULT f()"))
+        .comment("CONSTRAINT c CHECK ((name ~ 'x'::text)) -- no such function")
+        .definition("CONSTRAINT u UNIQUE (name)", None)
+        .trailer("~ 3 rows");
+    assert_eq!(
+        ddl.build(),
+        "-- a snapshot\n\
+        CREATE TABLE t (\n  \
+          -- dropped: x\n  \
+          id INTEGER PRIMARY KEY NOT NULL -- INT4\n  \
+          , name TEXT NOT NULL -- TEXT DEFAULT f()\n  \
+          -- CONSTRAINT c CHECK ((name ~ 'x'::text)) -- no such function\n  \
+          , CONSTRAINT u UNIQUE (name)\n\
+        ); -- ~ 3 rows\n"
+    );
+    // a line break in a comment can't end it
+    let mut ddl = CreateTable::new("t");
+    ddl.preamble("the rows of x.\"a\nDROP TABLE y;\"")
+        .definition("c TEXT", Some("TEXT DEFAULT 'a'\r\n|| 'b'::text"));
+    assert_eq!(
+        ddl.build(),
+        "-- the rows of x.\"a DROP TABLE y;\"\n\
+        CREATE TABLE t (\n  \
+          c TEXT -- TEXT DEFAULT 'a'  || 'b'::text\n\
+        );\n"
+    );
+}
+
+#[test]
+fn test_foreign_key_actions() {
+    let fk = FkeyConstraint {
+        name: "file_tag_tag_id_fkey".to_owned(),
+        schema: "public".to_owned(),
+        table: "file_tag".to_owned(),
+        columns: vec!["tag_id".to_owned()],
+        foreign_schema: "public".to_owned(),
+        foreign_table: "tag".to_owned(),
+        foreign_columns: vec!["id".to_owned()],
+        references: "tag".to_owned(),
+        inherited: false,
+        on_delete: None,
+        on_update: None,
+        match_type: None,
+        deferrable: false,
+        initially_deferred: false,
+    };
+    assert_eq!(
+        foreign_key(&fk),
+        "CONSTRAINT file_tag_tag_id_fkey FOREIGN KEY (tag_id) REFERENCES tag(id)"
+    );
+    let fk = FkeyConstraint {
+        on_delete: Some("SET NULL".to_owned()),
+        on_update: Some("CASCADE".to_owned()),
+        match_type: Some("FULL".to_owned()),
+        deferrable: true,
+        initially_deferred: true,
+        ..fk
+    };
+    assert_eq!(
+        foreign_key(&fk),
+        "CONSTRAINT file_tag_tag_id_fkey FOREIGN KEY (tag_id) REFERENCES tag(id) \
+        MATCH FULL ON DELETE SET NULL ON UPDATE CASCADE DEFERRABLE INITIALLY DEFERRED"
+    );
+}
@@ -0,0 +1,672 @@
+//! Translate postgres expressions, as `pg_get_expr` prints them, into SQLite.
+
+use crate::quote;
+use regex::Regex;
+
+/// Types that postgres prints as more than one word, e.g. in `::character varying`.
+const MULTI_WORD_TYPES: &[&str] = &[
+    "character varying",
+    "double precision",
+    "bit varying",
+    "timestamp without time zone",
+    "timestamp with time zone",
+    "time without time zone",
+    "time with time zone",
+];
+
+/// postgres' pattern-matching operators and their SQLite equivalents. SQLite's
+/// LIKE ignores the case of ASCII letters, so it stands in for ILIKE, too,
+/// and for LIKE only when the pattern isn't a literal `GLOB_OPERATORS` can
+/// take; see `approximation`.
+const LIKE_OPERATORS: &[(&str, &str)] = &[
+    ("!~~*", "NOT LIKE"),
+    ("!~~", "NOT LIKE"),
+    ("~~*", "LIKE"),
+    ("~~", "LIKE"),
+];
+
+/// The case-sensitive operators of `LIKE_OPERATORS`, for a literal pattern
+/// `glob_pattern` can rewrite.
+const GLOB_OPERATORS: &[(&str, &str)] = &[("!~~", "NOT GLOB"), ("~~", "GLOB")];
+
+/// A random (version 4) UUID, as text, for defaults like `gen_random_uuid()`
+/// that SQLite has no function for. `randomblob` isn't a cryptographically
+/// secure source, so this only suits ids that needn't be unguessable.
+const RANDOM_UUID: &str = "lower(hex(randomblob(4))) || '-' || lower(hex(randomblob(2))) \
+    || '-4' || substr(lower(hex(randomblob(2))), 2) \
+    || '-' || substr('89ab', 1 + (abs(random()) % 4), 1) || substr(lower(hex(randomblob(2))), 2) \
+    || '-' || lower(hex(randomblob(6)))";
+
+/// postgres functions that are often used as defaults, and SQLite stand-ins.
+const DEFAULT_FUNCTIONS: &[(&str, &str)] = &[
+    ("now()", "CURRENT_TIMESTAMP"),
+    ("transaction_timestamp()", "CURRENT_TIMESTAMP"),
+    ("statement_timestamp()", "CURRENT_TIMESTAMP"),
+    ("clock_timestamp()", "CURRENT_TIMESTAMP"),
+    ("LOCALTIMESTAMP", "CURRENT_TIMESTAMP"),
+    ("LOCALTIME", "CURRENT_TIME"),
+    ("gen_random_uuid()", RANDOM_UUID),
+    ("uuid_generate_v4()", RANDOM_UUID),
+];
+
+/// A piece of postgres SQL, as `tokens` splits it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token<'a> {
+    /// a string literal, quotes and all, e.g. `'it''s'` or `E'a\'b'`
+    Literal(&'a str),
+    /// a double-quoted identifier, quotes and all
+    Quoted(&'a str),
+    /// a keyword, a bare identifier, or a number
+    Word(&'a str),
+    Space(&'a str),
+    /// `::`, a run of operator characters, or one other character
+    Symbol(&'a str),
+}
+
+const OPERATOR_CHARS: &str = "+-*/<>=~!@#%^&|`?";
+
+impl<'a> Token<'a> {
+    fn text(self) -> &'a str {
+        match self {
+            Token::Literal(s) | Token::Quoted(s) | Token::Word(s) => return s,
+            Token::Space(s) | Token::Symbol(s) => return s,
+        }
+    }
+
+    /// The name a bare or quoted identifier stands for; postgres folds bare
+    /// names to lowercase.
+    fn name(self) -> Option<String> {
+        match self {
+            Token::Word(w) if !w.starts_with(|c: char| c.is_ascii_digit()) => {
+                return Some(w.to_lowercase())
+            }
+            Token::Quoted(q) => return Some(q[1..q.len() - 1].replace("\"\"", "\"")),
+            _ => return None,
+        }
+    }
+}
+
+/// The length of the quoted string or identifier at the start of `sql`,
+/// which ends at the first unpaired `quote`, or at the end of `sql`. With
+/// `backslashes`, as in an `E'...'` string, a backslash escapes what follows.
+fn quoted_len(sql: &str, quote: char, backslashes: bool) -> usize {
+    let mut chars = sql.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        if backslashes && c == '\\' {
+            chars.next();
+        } else if c == quote {
+            if sql[i + 1..].starts_with(quote) {
+                chars.next();
+            } else {
+                return i + 1;
+            }
+        }
+    }
+    return sql.len();
+}
+
+/// Split `sql` into tokens, so rewrites can leave string literals and quoted
+/// identifiers alone. The tokens' text, concatenated, is `sql`.
+fn tokens<'a>(sql: &'a str) -> Vec<Token<'a>> {
+    let mut tokens = vec![];
+    let mut rest = sql;
+    while let Some(c) = rest.chars().next() {
+        let word = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+        let (kind, len): (fn(&'a str) -> Token<'a>, usize) = match c {
+            '\'' => (Token::Literal, quoted_len(rest, '\'', false)),
+            'E' | 'e' if rest[1..].starts_with('\'') => {
+                (Token::Literal, 1 + quoted_len(&rest[1..], '\'', true))
+            }
+            '"' => (Token::Quoted, quoted_len(rest, '"', false)),
+            ':' if rest.starts_with("::") => (Token::Symbol, 2),
+            c if word(c) => (Token::Word, rest.find(|c| !word(c)).unwrap_or(rest.len())),
+            c if c.is_whitespace() => (
+                Token::Space,
+                rest.find(|c: char| !c.is_whitespace())
+                    .unwrap_or(rest.len()),
+            ),
+            c if OPERATOR_CHARS.contains(c) => (
+                Token::Symbol,
+                rest.find(|c| !OPERATOR_CHARS.contains(c))
+                    .unwrap_or(rest.len()),
+            ),
+            c => (Token::Symbol, c.len_utf8()),
+        };
+        tokens.push(kind(&rest[..len]));
+        rest = &rest[len..];
+    }
+    return tokens;
+}
+
+/// Rewrite each `schema.relation` in a view's definition, as `pg_get_viewdef`
+/// prints it with an empty search_path, to the name `rename` returns for it,
+/// leaving those it returns `None` for as they are. postgres qualifies the
+/// columns of a relation without an alias with the relation's own name, so a
+/// renamed one is given its old name as an alias.
+pub fn rename_relations<F>(defn: &str, mut rename: F) -> Result<String, String>
+where
+    F: FnMut(&str, &str) -> Result<Option<String>, String>,
+{
+    let tokens = tokens(defn);
+    let mut sqlite = String::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let qualified = tokens.get(i + 1) == Some(&Token::Symbol("."))
+            && (i == 0 || tokens[i - 1] != Token::Symbol("."))
+            && !matches!(
+                tokens.get(i + 3),
+                Some(Token::Symbol(".")) | Some(Token::Symbol("("))
+            );
+        let names = match (tokens[i].name(), tokens.get(i + 2).and_then(|t| t.name())) {
+            (Some(schema), Some(relation)) if qualified => Some((schema, relation)),
+            _ => None,
+        };
+        let renamed = match &names {
+            Some((schema, relation)) => rename(schema, relation)?,
+            None => None,
+        };
+        let (renamed, relation) = match (renamed, names) {
+            (Some(renamed), Some((_, relation))) => (renamed, relation),
+            _ => {
+                sqlite.push_str(tokens[i].text());
+                i += 1;
+                continue;
+            }
+        };
+        i += 3;
+        // postgres quotes any keyword it prints as an alias
+        let aliased = match tokens[i..].iter().find(|t| !matches!(t, Token::Space(_))) {
+            Some(Token::Quoted(_)) => true,
+            Some(Token::Word(w)) => !quote::pg_keyword(w),
+            _ => false,
+        };
+        let alias = quote::sqlite(&relation);
+        sqlite.push_str(&renamed);
+        if !aliased && renamed != alias {
+            sqlite.push_str(&format!(" AS {}", alias));
+        }
+    }
+    return Ok(sqlite);
+}
+
+/// The index just past the type of a `::type` cast whose type starts at
+/// `tokens[i]`, e.g. `public.money_amount`, `character varying(10)`, or `text[]`.
+fn skip_cast_type(tokens: &[Token], mut i: usize) -> usize {
+    let first = match tokens.get(i) {
+        Some(Token::Word(w)) => w.to_lowercase(),
+        Some(Token::Quoted(_)) => "".to_owned(),
+        _ => return i,
+    };
+    i += 1;
+    if tokens.get(i) == Some(&Token::Symbol("."))
+        && tokens.get(i + 1).and_then(|t| t.name()).is_some()
+    {
+        i += 2; // a type in another schema
+    }
+    let modifiers = |i: usize| match tokens.get(i) {
+        Some(Token::Symbol("(")) => tokens[i..]
+            .iter()
+            .position(|t| *t == Token::Symbol(")"))
+            .map_or(tokens.len(), |end| i + end + 1),
+        _ => i,
+    };
+    i = modifiers(i); // e.g. `timestamp(3) with time zone`
+    for name in MULTI_WORD_TYPES {
+        let words: Vec<&str> = name.split(' ').collect();
+        let rest = &words[1..];
+        let matches = words[0] == first
+            && rest.iter().enumerate().all(|(n, word)| {
+                return matches!(tokens.get(i + 2 * n), Some(Token::Space(_)))
+                    && matches!(tokens.get(i + 2 * n + 1), Some(Token::Word(w)) if w.eq_ignore_ascii_case(word));
+            });
+        if matches {
+            i += 2 * rest.len();
+            break;
+        }
+    }
+    i = modifiers(i);
+    while tokens.get(i) == Some(&Token::Symbol("["))
+        && tokens.get(i + 1) == Some(&Token::Symbol("]"))
+    {
+        i += 2;
+    }
+    return i;
+}
+
+/// The index of the pattern of the `~~` or `!~~` at `tokens[i]`, and the
+/// pattern as SQLite's case-sensitive GLOB would take it, if it's a plain
+/// string literal: `%` becomes `*`, `_` becomes `?`, and a backslash, LIKE's
+/// default escape, makes what follows it match only itself.
+fn glob_pattern(tokens: &[Token], i: usize) -> Option<(usize, String)> {
+    let j = i
+        + 1
+        + tokens[i + 1..]
+            .iter()
+            .position(|t| !matches!(t, Token::Space(_)))?;
+    let literal = match tokens[j] {
+        Token::Literal(literal) if literal.starts_with('\'') => literal,
+        _ => return None,
+    };
+    let mut glob = String::new();
+    let mut chars = literal.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '%' => {
+                glob.push('*');
+                continue;
+            }
+            '_' => {
+                glob.push('?');
+                continue;
+            }
+            '\\' => chars.next().unwrap_or(c),
+            c => c,
+        };
+        match c {
+            '*' | '?' | '[' => glob.push_str(&format!("[{}]", c)),
+            c => glob.push(c),
+        }
+    }
+    return Some((j, glob));
+}
+
+/// Rewrite the parts of `expr` that postgres spells differently from SQLite:
+/// casts are dropped (SQLite's column affinity does the converting),
+/// `= ANY (ARRAY[...])` becomes `IN (...)`, `~~` becomes `GLOB` when its
+/// pattern is a literal and `LIKE` otherwise, and `~~*` becomes `LIKE`.
+/// String literals and quoted names are left alone, and anything else is
+/// left as-is for `validate` to catch.
+pub fn to_sqlite(expr: &str) -> String {
+    let tokens = tokens(expr);
+    let mut sqlite = String::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let glob = GLOB_OPERATORS
+            .iter()
+            .find(|(op, _)| tokens[i] == Token::Symbol(op))
+            .and_then(|(_, glob)| Some((glob, glob_pattern(&tokens, i)?)));
+        if let Some((glob, (j, pattern))) = glob {
+            sqlite.push_str(glob);
+            tokens[i + 1..j]
+                .iter()
+                .for_each(|t| sqlite.push_str(t.text()));
+            sqlite.push_str(&pattern);
+            i = j + 1;
+            continue;
+        }
+        let like = LIKE_OPERATORS
+            .iter()
+            .find(|(op, _)| tokens[i] == Token::Symbol(op));
+        match (tokens[i], like) {
+            (Token::Symbol("::"), _) => {
+                i = skip_cast_type(&tokens, i + 1);
+                continue;
+            }
+            (_, Some((_, like))) => sqlite.push_str(like),
+            (token, None) => sqlite.push_str(token.text()),
+        }
+        i += 1;
+    }
+    // the array is parenthesized when it had a cast, e.g. `= ANY ((ARRAY['a'])::text[])`
+    let array = r"\((?:ARRAY\[([^\]]*)\]|\(ARRAY\[([^\]]*)\]\))\)";
+    let any = Regex::new(&format!(r"= ANY {}", array)).unwrap();
+    let all = Regex::new(&format!(r"<> ALL {}", array)).unwrap();
+    sqlite = any.replace_all(&sqlite, "IN (${1}${2})").into_owned();
+    sqlite = all.replace_all(&sqlite, "NOT IN (${1}${2})").into_owned();
+    return sqlite;
+}
+
+/// How SQLite evaluates `to_sqlite(expr)` differently from how postgres
+/// evaluates `expr`, if it does, for reporting.
+pub fn approximation(expr: &str) -> Option<&'static str> {
+    let tokens = tokens(expr);
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Symbol("~~*") | Token::Symbol("!~~*") => {
+                return Some("ILIKE became LIKE, which only ignores the case of ASCII letters");
+            }
+            Token::Symbol("~~") | Token::Symbol("!~~") if glob_pattern(&tokens, i).is_none() => {
+                return Some("LIKE with a pattern that isn't a literal ignores case in SQLite");
+            }
+            _ => {}
+        }
+    }
+    return None;
+}
+
+/// Replace each column reference in `expr`, a bare or quoted name that isn't
+/// qualifying another or calling a function, with what `rewrite` returns for
+/// it, leaving those it returns `None` for as they are.
+pub fn rewrite_columns<F>(expr: &str, mut rewrite: F) -> Result<String, String>
+where
+    F: FnMut(&str) -> Result<Option<String>, String>,
+{
+    let tokens = tokens(expr);
+    let mut sqlite = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let reference = (i == 0 || tokens[i - 1] != Token::Symbol("."))
+            && !matches!(
+                tokens.get(i + 1),
+                Some(Token::Symbol(".")) | Some(Token::Symbol("("))
+            );
+        let rewritten = match token.name() {
+            Some(name) if reference => rewrite(&name)?,
+            _ => None,
+        };
+        match rewritten {
+            Some(rewritten) => sqlite.push_str(&rewritten),
+            None => sqlite.push_str(token.text()),
+        }
+    }
+    return Ok(sqlite);
+}
+
+/// Replace each string literal that `expr` casts, e.g. `'2000-01-01'::date`,
+/// with what `rewrite` returns for the literal and the first word of the
+/// type, lowercased, keeping the cast for `to_sqlite` to drop.
+pub fn rewrite_typed_literals<F>(expr: &str, mut rewrite: F) -> Result<String, String>
+where
+    F: FnMut(&str, &str) -> Result<Option<String>, String>,
+{
+    let tokens = tokens(expr);
+    let mut sqlite = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let rewritten = match (token, tokens.get(i + 1), tokens.get(i + 2)) {
+            (Token::Literal(literal), Some(Token::Symbol("::")), Some(Token::Word(type_name))) => {
+                rewrite(literal, &type_name.to_lowercase())?
+            }
+            _ => None,
+        };
+        match rewritten {
+            Some(rewritten) => sqlite.push_str(&rewritten),
+            None => sqlite.push_str(token.text()),
+        }
+    }
+    return Ok(sqlite);
+}
+
+/// An empty table `t` with `columns` in an in-memory database, to try
+/// translated expressions against.
+fn scratch_table(columns: &[String]) -> Result<rusqlite::Connection, String> {
+    let conn = rusqlite::Connection::open_in_memory().map_err(|e| e.to_string())?;
+    let columns: Vec<String> = columns.iter().map(|c| quote::sqlite(c)).collect();
+    conn.execute_batch(&format!("CREATE TABLE t ({})", columns.join(", ")))
+        .map_err(|e| e.to_string())?;
+    return Ok(conn);
+}
+
+/// Check that SQLite can evaluate `expr` in a table with `columns`, so
+/// unknown functions, operators, and columns are caught before DEST is written.
+pub fn validate(expr: &str, columns: &[String]) -> Result<(), String> {
+    let conn = scratch_table(columns)?;
+    let prepared = conn.prepare(&format!("SELECT ({}) FROM t", expr));
+    match prepared {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(e.to_string()),
+    }
+}
+
+/// Check that SQLite can build an index on `keys`, limited to rows matching
+/// `predicate`, in a table with `columns`. This catches what `validate`
+/// does, plus functions SQLite won't index because they aren't deterministic.
+pub fn validate_index(
+    keys: &[String],
+    predicate: Option<&str>,
+    columns: &[String],
+) -> Result<(), String> {
+    let conn = scratch_table(columns)?;
+    let predicate = match predicate {
+        Some(predicate) => format!(" WHERE {}", predicate),
+        None => "".to_owned(),
+    };
+    let create = format!("CREATE INDEX i ON t ({}){}", keys.join(", "), predicate);
+    return conn.execute_batch(&create).map_err(|e| e.to_string());
+}
+
+/// Translate a column's default, as `information_schema.columns` prints it,
+/// into something to follow `DEFAULT` in SQLite. Literals are left bare and
+/// anything else is parenthesized, as SQLite requires of expressions.
+pub fn default_to_sqlite(default: &str) -> Result<String, String> {
+    if default.starts_with("nextval(") {
+        return Err("sequences aren't copied".to_owned());
+    }
+    let translated = to_sqlite(default);
+    let tokens = tokens(&translated);
+    let mut sqlite = String::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        // a name, and `(` and `)` if it's called
+        let function = DEFAULT_FUNCTIONS.iter().find_map(|(pg, lite)| {
+            let (name, call) = match pg.strip_suffix("()") {
+                Some(name) => (name, &[Token::Symbol("("), Token::Symbol(")")][..]),
+                None => (*pg, &[][..]),
+            };
+            let matches = matches!(tokens[i], Token::Word(w) if w.eq_ignore_ascii_case(name))
+                && tokens.get(i + 1..i + 1 + call.len()) == Some(call);
+            return if matches {
+                Some((lite, 1 + call.len()))
+            } else {
+                None
+            };
+        });
+        match function {
+            Some((lite, len)) => {
+                sqlite.push_str(lite);
+                i += len;
+            }
+            None => {
+                sqlite.push_str(tokens[i].text());
+                i += 1;
+            }
+        }
+    }
+    let literal = Regex::new(
+        r"^(?:'(?:[^']|'')*'|-?\d+(?:\.\d+)?|NULL|true|false|CURRENT_(?:TIMESTAMP|DATE|TIME))$",
+    )
+    .unwrap();
+    if !literal.is_match(&sqlite) {
+        sqlite = format!("({})", sqlite);
+    }
+    // SQLite only looks up functions once a default is used
+    let conn = rusqlite::Connection::open_in_memory().map_err(|e| e.to_string())?;
+    conn.execute_batch(&format!(
+        "CREATE TABLE t (c DEFAULT {}); INSERT INTO t DEFAULT VALUES;",
+        sqlite
+    ))
+    .map_err(|e| e.to_string())?;
+    return Ok(sqlite);
+}
+
+/// What to do with a constraint whose expression SQLite can't evaluate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Untranslatable {
+    /// leave it out
+    Drop,
+    /// keep postgres' definition as a comment in the `CREATE TABLE` statement
+    Comment,
+}
+
+pub const UNTRANSLATABLE_POLICIES: &[&str] = &["comment", "drop"];
+
+impl Untranslatable {
+    pub fn parse(policy: &str) -> Untranslatable {
+        match policy {
+            "drop" => Untranslatable::Drop,
+            "comment" => Untranslatable::Comment,
+            other => panic!("unknown untranslatable expression policy {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_translating_check_expressions() {
+    assert_eq!(to_sqlite("(price > (0)::numeric)"), "(price > (0))");
+    assert_eq!(
+        to_sqlite("((code)::text ~~ 'A%'::text)"),
+        "((code) GLOB 'A*')"
+    );
+    assert_eq!(
+        to_sqlite(r"((code)::text !~~ 'a\_b*[_'::text)"),
+        "((code) NOT GLOB 'a_b[*][[]?')"
+    );
+    assert_eq!(
+        to_sqlite("(status = ANY (ARRAY['new'::character varying, 'done'::character varying]))"),
+        "(status IN ('new', 'done'))"
+    );
+    assert_eq!(
+        to_sqlite("((status)::text <> ALL ((ARRAY['x'::character varying])::text[]))"),
+        "((status) NOT IN ('x'))"
+    );
+    assert_eq!(
+        to_sqlite("(created > '2020-01-01 00:00:00'::timestamp without time zone)"),
+        "(created > '2020-01-01 00:00:00')"
+    );
+    // inside string literals and quoted names, `::` and `~~` are just text
+    assert_eq!(
+        to_sqlite("((\"a::b\")::text <> 'a::b ~~ c'::text)"),
+        "((\"a::b\") <> 'a::b ~~ c')"
+    );
+    assert_eq!(
+        to_sqlite("(amount < (100)::public.money_amount)"),
+        "(amount < (100))"
+    );
+    assert_eq!(
+        to_sqlite("(at > '2020-01-01'::timestamp(3) with time zone)"),
+        "(at > '2020-01-01')"
+    );
+    assert_eq!(
+        to_sqlite("((code)::character varying(10) !~~* 'a%'::text)"),
+        "((code) NOT LIKE 'a%')"
+    );
+    assert_eq!(
+        approximation("((code)::text ~~* 'a%'::text)"),
+        Some("ILIKE became LIKE, which only ignores the case of ASCII letters")
+    );
+    assert_eq!(approximation("((code)::text ~~ 'a%'::text)"), None);
+    assert_eq!(
+        to_sqlite("((code)::text ~~ (prefix || '%'::text))"),
+        "((code) LIKE (prefix || '%'))"
+    );
+    assert_eq!(
+        approximation("((code)::text ~~ (prefix || '%'::text))"),
+        Some("LIKE with a pattern that isn't a literal ignores case in SQLite")
+    );
+    let columns = vec!["price".to_owned(), "status".to_owned()];
+    assert!(validate("(price > (0))", &columns).is_ok());
+    assert!(validate("(status IN ('new', 'done'))", &columns).is_ok());
+    assert!(validate("(price > 0 AND missing > 0)", &columns).is_err());
+    assert!(validate("(status ~ '^[a-z]+$')", &columns).is_err());
+    assert!(validate("(isfinite(price))", &columns).is_err());
+}
+
+#[test]
+fn test_translating_defaults() {
+    assert_eq!(default_to_sqlite("'x'::text"), Ok("'x'".to_owned()));
+    assert_eq!(
+        default_to_sqlite("now()"),
+        Ok("CURRENT_TIMESTAMP".to_owned())
+    );
+    assert_eq!(
+        default_to_sqlite("LOCALTIMESTAMP"),
+        Ok("CURRENT_TIMESTAMP".to_owned())
+    );
+    assert_eq!(
+        default_to_sqlite("CURRENT_DATE"),
+        Ok("CURRENT_DATE".to_owned())
+    );
+    assert_eq!(default_to_sqlite("(-1)"), Ok("((-1))".to_owned()));
+    assert_eq!(default_to_sqlite("'{}'::jsonb"), Ok("'{}'".to_owned()));
+    assert_eq!(default_to_sqlite("false"), Ok("false".to_owned()));
+    // names inside string literals are just text
+    assert_eq!(
+        default_to_sqlite("('call now() at '::text || now())"),
+        Ok("(('call now() at ' || CURRENT_TIMESTAMP))".to_owned())
+    );
+    assert_eq!(
+        default_to_sqlite("'LOCALTIME'::text"),
+        Ok("'LOCALTIME'".to_owned())
+    );
+    assert!(default_to_sqlite("gen_random_uuid()")
+        .unwrap()
+        .contains("randomblob"));
+    assert!(default_to_sqlite("nextval('t_id_seq'::regclass)").is_err());
+    assert!(default_to_sqlite("timezone('utc'::text, now())").is_err());
+    assert!(default_to_sqlite("my_now()").is_err());
+}
+
+#[test]
+fn test_rewriting_columns() {
+    let rewrite = |expr: &str| {
+        return rewrite_columns(expr, |column| match column {
+            "amount" | "Fee" => Ok(Some(format!("CAST({} AS REAL)", quote::sqlite(column)))),
+            "blob" => Err("stored as a blob".to_owned()),
+            _ => Ok(None),
+        });
+    };
+    assert_eq!(
+        rewrite(r#"((amount > 0) AND ("Fee" < abs(amount)) AND (t.amount <> 'amount'))"#),
+        Ok(r#"((CAST(amount AS REAL) > 0) AND (CAST(Fee AS REAL) < abs(CAST(amount AS REAL))) AND (t.amount <> 'amount'))"#.to_owned())
+    );
+    assert!(rewrite("(blob IS NOT NULL)").is_err());
+}
+
+#[test]
+fn test_rewriting_typed_literals() {
+    let rewrite = |expr: &str| {
+        return rewrite_typed_literals(expr, |literal, type_name| match type_name {
+            "date" => Ok(Some(format!("julianday({})", literal))),
+            _ => Ok(None),
+        });
+    };
+    assert_eq!(
+        rewrite("((d > '2000-01-01'::date) AND (name <> 'a'::text))"),
+        Ok("((d > julianday('2000-01-01')::date) AND (name <> 'a'::text))".to_owned())
+    );
+}
+
+#[test]
+fn test_renaming_relations_in_views() {
+    let prefix = |schema: &str, relation: &str| -> Result<Option<String>, String> {
+        match (schema, relation) {
+            ("public", _) | ("My Schema", _) => {
+                return Ok(Some(quote::sqlite(&format!("{}__{}", schema, relation))))
+            }
+            ("audit", _) => return Err(format!("{}.{} is elsewhere", schema, relation)),
+            _ => return Ok(None),
+        }
+    };
+    assert_eq!(
+        rename_relations(
+            " SELECT f.id,\n    _directory.absolute_path\n   FROM (public._file f\n     \
+            JOIN public._directory ON ((f.directory_id = _directory.id)))\n  \
+            WHERE (f.name <> 'public.x'::text);",
+            prefix
+        ),
+        Ok(
+            " SELECT f.id,\n    _directory.absolute_path\n   FROM (public___file f\n     \
+            JOIN public___directory AS _directory ON ((f.directory_id = _directory.id)))\n  \
+            WHERE (f.name <> 'public.x'::text);"
+                .to_owned()
+        )
+    );
+    assert_eq!(
+        rename_relations(" SELECT \"Order\".id FROM \"My Schema\".\"Order\";", prefix),
+        Ok(" SELECT \"Order\".id FROM \"My Schema__Order\" AS \"Order\";".to_owned())
+    );
+    // nor are columns, or functions
+    assert_eq!(
+        rename_relations(" SELECT t.id, public.f(t.id) FROM other.t;", prefix),
+        Ok(" SELECT t.id, public.f(t.id) FROM other.t;".to_owned())
+    );
+    assert!(rename_relations(" SELECT 1 FROM audit.elsewhere;", prefix).is_err());
+}
+
+#[test]
+fn test_validating_indexes() {
+    let columns = vec!["code".to_owned(), "status".to_owned()];
+    let keys = vec!["lower(code) DESC".to_owned(), "status".to_owned()];
+    assert!(validate_index(&keys, None, &columns).is_ok());
+    assert!(validate_index(&keys[1..], Some("(status = 'new')"), &columns).is_ok());
+    assert!(validate_index(&["random()".to_owned()], None, &columns).is_err());
+    assert!(validate_index(&["code".to_owned()], Some("(code ~ 'x')"), &columns).is_err());
+}
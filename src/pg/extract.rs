@@ -0,0 +1,199 @@
+//! Read every row of a table, either with a `SELECT` or with a binary `COPY`.
+
+use super::array;
+use super::datetime::{self, Datetime};
+use super::object_types::{Binder, CellSource};
+use super::{ColInfo, SqlError, Table};
+use crate::quote;
+use fallible_iterator::FallibleIterator;
+use postgres::binary_copy::{BinaryCopyOutIter, BinaryCopyOutRow};
+use postgres::types::{FromSql as FromPgSql, Type as PgType};
+use rusqlite::types::{ToSqlOutput, Value, ValueRef};
+use rusqlite::Statement;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Extractor {
+    /// `COPY (SELECT ...) TO STDOUT (FORMAT binary)`: rows arrive without
+    /// per-row column metadata, which is much cheaper on wide tables
+    Copy,
+    /// a prepared `SELECT`, read one `Row` at a time
+    Select,
+}
+
+pub const EXTRACTORS: &[&str] = &["copy", "select"];
+
+impl Extractor {
+    pub fn parse(name: &str) -> Extractor {
+        match name {
+            "copy" => Extractor::Copy,
+            "select" => Extractor::Select,
+            other => panic!("unknown extractor {:?}", other),
+        }
+    }
+}
+
+enum Rows<'a> {
+    Select(postgres::RowIter<'a>),
+    Copy(BinaryCopyOutIter<'a>),
+}
+
+enum AnyRow {
+    Select(postgres::Row),
+    Copy(BinaryCopyOutRow),
+}
+
+impl CellSource for AnyRow {
+    fn try_get_cell<'a, T: FromPgSql<'a>>(&'a self, index: usize) -> Result<T, postgres::Error> {
+        match self {
+            AnyRow::Select(row) => return row.try_get_cell(index),
+            AnyRow::Copy(row) => return row.try_get_cell(index),
+        }
+    }
+}
+
+/// The rows of one table, decoded for SQLite as they're read.
+pub struct TableRows<'a> {
+    rows: Rows<'a>,
+    table: &'a str,
+    cols: Vec<&'a ColInfo>,
+    binders: Vec<Binder>,
+}
+
+impl TableRows<'_> {
+    fn next_row(&mut self) -> Result<Option<AnyRow>, postgres::Error> {
+        let row = match &mut self.rows {
+            Rows::Select(rows) => rows.next()?.map(AnyRow::Select),
+            Rows::Copy(rows) => rows.next()?.map(AnyRow::Copy),
+        };
+        if row.is_none() {
+            self.report_nulled();
+        }
+        return Ok(row);
+    }
+
+    /// Report the columns that had values SQLite has no equivalent for.
+    fn report_nulled(&mut self) {
+        for (col, binder) in self.cols.iter().zip(&mut self.binders) {
+            let nulled = std::mem::take(&mut binder.nulled);
+            if nulled > 0 {
+                println!(
+                    "-- stored {} NaN or infinite values of {}.{} as NULL",
+                    nulled, self.table, col.name
+                );
+            }
+        }
+    }
+
+    fn decode_each<F>(&mut self, row: &AnyRow, mut each: F) -> Result<(), SqlError>
+    where
+        F: FnMut(usize, ValueRef) -> Result<(), SqlError>,
+    {
+        for (index, binder) in self.binders.iter_mut().enumerate() {
+            match binder.cell(row, index) {
+                Ok(cell) => each(index, cell)?,
+                Err(e) => panic!(
+                    "error {} in column {} ({} {})",
+                    e, index, self.cols[index].name, self.cols[index].data_type
+                ),
+            }
+        }
+        return Ok(());
+    }
+
+    /// Bind the next row to the parameters of `statement`; false once every
+    /// row has been read.
+    pub fn bind_next(&mut self, statement: &mut Statement) -> Result<bool, SqlError> {
+        let row = match self.next_row()? {
+            Some(row) => row,
+            None => return Ok(false),
+        };
+        self.decode_each(&row, |index, cell| {
+            return Ok(statement.raw_bind_parameter(index + 1, ToSqlOutput::Borrowed(cell))?);
+        })?;
+        return Ok(true);
+    }
+
+    /// The next row as owned values, which can be sent to another thread.
+    pub fn next_values(&mut self) -> Result<Option<Vec<Value>>, SqlError> {
+        let row = match self.next_row()? {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let mut values = Vec::with_capacity(self.binders.len());
+        self.decode_each(&row, |_, cell| {
+            values.push(cell.into());
+            return Ok(());
+        })?;
+        return Ok(Some(values));
+    }
+}
+
+pub fn dump_table<'a>(
+    conn: &'a mut postgres::Transaction<'_>,
+    table: &'a Table,
+    extractor: Extractor,
+) -> Result<TableRows<'a>, postgres::Error> {
+    let cols: Vec<&ColInfo> = table
+        .column_order
+        .iter()
+        .map(|name| table.columns.get(name).unwrap())
+        .collect();
+    let binders: Vec<Binder> = cols
+        .iter()
+        .map(|col| match (col.numeric, col.datetime) {
+            (Some(storage), _) => Binder::numeric(storage, col.scale),
+            (None, Some(storage)) => Binder::datetime(&col.data_type, storage),
+            (None, None) if array::element_type(&col.data_type).is_some() => {
+                Binder::new(&PgType::JSON).unwrap()
+            }
+            (None, None) => Binder::new(&col.data_type)
+                .unwrap_or_else(|e| panic!("{} (column {}.{})", e, table.sqlite_name, col.name)),
+        })
+        .collect();
+    let columns: Vec<String> = cols
+        .iter()
+        .map(|col| {
+            if table.partition_column.as_ref() == Some(&col.name) {
+                return "tableoid::regclass::text".to_owned(); // the partition the row came from
+            }
+            let column = array::select_column(table, &col.name);
+            if col.datetime == Some(Datetime::TextWithOffset) {
+                return datetime::select_with_offset(&column);
+            }
+            if array::element_type(&col.data_type).is_some() {
+                return array::select_as_json(&column);
+            }
+            return column;
+        })
+        .collect();
+    // without `only`, a parent's rows include its children's, which are copied separately
+    let only = if table.include_children { "" } else { "only " };
+    let relation = format!("{}.{}", quote::pg(&table.schema), quote::pg(&table.name));
+    let from = array::from_elements(table, &format!("{}{}", only, relation));
+    let select = format!("select {} from {}", columns.join(", "), from);
+    let rows = match extractor {
+        Extractor::Select => {
+            let statement = conn.prepare(&select)?;
+            let params: Vec<&str> = vec![];
+            Rows::Select(conn.query_raw(&statement, params.iter())?)
+        }
+        Extractor::Copy => {
+            let types: Vec<PgType> = cols
+                .iter()
+                .map(|col| match col.datetime {
+                    Some(Datetime::TextWithOffset) => PgType::TEXT,
+                    _ if array::element_type(&col.data_type).is_some() => PgType::JSON,
+                    _ => col.data_type.clone(),
+                })
+                .collect();
+            let reader = conn.copy_out(&*format!("COPY ({}) TO STDOUT (FORMAT binary)", select))?;
+            Rows::Copy(BinaryCopyOutIter::new(reader, &types))
+        }
+    };
+    return Ok(TableRows {
+        rows,
+        table: &table.sqlite_name,
+        cols,
+        binders,
+    });
+}
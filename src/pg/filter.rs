@@ -0,0 +1,296 @@
+//! Choose which relations to copy with `--include-*` and `--exclude-*` patterns.
+
+use super::{FkeyConstraint, Naming, Rel, View, ViewRelUsage};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+/// A glob like `audit_*`, or a regex between slashes like `/^stg_\d+$/`.
+/// Patterns are tried against both `relation` and `schema.relation`.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    regex: Regex,
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut re = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            '[' => {
+                re.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    re.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    if c == '\\' || c == '[' {
+                        re.push('\\');
+                    }
+                    re.push(c);
+                }
+                re.push(']');
+            }
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    return re;
+}
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Result<Pattern, String> {
+        let re = if pattern.len() > 1 && pattern.starts_with('/') && pattern.ends_with('/') {
+            pattern[1..pattern.len() - 1].to_owned()
+        } else {
+            glob_to_regex(pattern)
+        };
+        match Regex::new(&re) {
+            Ok(regex) => Ok(Pattern { regex }),
+            Err(e) => Err(format!("invalid pattern {:?}: {}", pattern, e)),
+        }
+    }
+    pub fn matches(&self, schema: &str, name: &str) -> bool {
+        return self.regex.is_match(name) || self.regex.is_match(&format!("{}.{}", schema, name));
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RelFilter {
+    pub include_tables: Vec<Pattern>,
+    pub exclude_tables: Vec<Pattern>,
+    pub include_views: Vec<Pattern>,
+    pub exclude_views: Vec<Pattern>,
+}
+
+fn passes(includes: &[Pattern], excludes: &[Pattern], schema: &str, name: &str) -> bool {
+    let included = includes.is_empty() || includes.iter().any(|p| p.matches(schema, name));
+    return included && !excludes.iter().any(|p| p.matches(schema, name));
+}
+
+impl RelFilter {
+    pub fn keep(&self, rel: &Rel) -> bool {
+        match rel.relkind.as_str() {
+            "view" | "materialized view" => passes(
+                &self.include_views,
+                &self.exclude_views,
+                &rel.schema,
+                &rel.name,
+            ),
+            _ => passes(
+                &self.include_tables,
+                &self.exclude_tables,
+                &rel.schema,
+                &rel.name,
+            ),
+        }
+    }
+}
+
+/// What to do about a foreign key from a copied table to one that isn't copied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExcludedFk {
+    /// leave the foreign key out of the copied table
+    Drop,
+    /// copy the referenced table too, even if a pattern excluded it
+    Include,
+    /// stop without copying anything
+    Fail,
+}
+
+pub const EXCLUDED_FK_POLICIES: &[&str] = &["drop", "include", "fail"];
+
+impl ExcludedFk {
+    pub fn parse(policy: &str) -> ExcludedFk {
+        match policy {
+            "drop" => ExcludedFk::Drop,
+            "include" => ExcludedFk::Include,
+            "fail" => ExcludedFk::Fail,
+            other => panic!("unknown excluded foreign key policy {:?}", other),
+        }
+    }
+}
+
+/// How to copy a materialized view.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MaterializedViews {
+    /// as a table holding the view's current rows
+    Table,
+    /// as a SQLite view, from the view's definition
+    View,
+}
+
+pub const MATERIALIZED_VIEW_POLICIES: &[&str] = &["table", "view"];
+
+impl MaterializedViews {
+    pub fn parse(policy: &str) -> MaterializedViews {
+        match policy {
+            "table" => MaterializedViews::Table,
+            "view" => MaterializedViews::View,
+            other => panic!("unknown materialized view policy {:?}", other),
+        }
+    }
+}
+
+/// Whether to copy foreign tables, which means reading every row from
+/// wherever postgres gets them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ForeignTables {
+    Skip,
+    /// as tables holding their current rows
+    Copy,
+}
+
+pub const FOREIGN_TABLE_POLICIES: &[&str] = &["skip", "copy"];
+
+impl ForeignTables {
+    pub fn parse(policy: &str) -> ForeignTables {
+        match policy {
+            "skip" => ForeignTables::Skip,
+            "copy" => ForeignTables::Copy,
+            other => panic!("unknown foreign table policy {:?}", other),
+        }
+    }
+}
+
+/// Apply `filter` to `rels`, then resolve foreign keys that point from the
+/// remaining tables to excluded ones according to `policy`.
+pub fn select_relations(
+    rels: Vec<Rel>,
+    fkeys: &[FkeyConstraint],
+    filter: &RelFilter,
+    policy: ExcludedFk,
+    naming: &Naming,
+) -> Vec<Rel> {
+    let available: HashSet<String> = rels
+        .iter()
+        .map(|r| naming.sqlite_name(&r.schema, &r.name))
+        .collect();
+    let mut kept: HashSet<String> = rels
+        .iter()
+        .filter(|r| filter.keep(r))
+        .map(|r| naming.sqlite_name(&r.schema, &r.name))
+        .collect();
+    loop {
+        let dangling: Vec<&FkeyConstraint> = fkeys
+            .iter()
+            .filter(|fk| kept.contains(&fk.table) && !kept.contains(&fk.foreign_table))
+            .collect();
+        if dangling.is_empty() || policy == ExcludedFk::Drop {
+            break;
+        }
+        let describe =
+            |fk: &&FkeyConstraint| format!("{} ({} -> {})", fk.name, fk.table, fk.foreign_table);
+        let unavailable: Vec<String> = dangling
+            .iter()
+            .filter(|fk| !available.contains(&fk.foreign_table))
+            .map(describe)
+            .collect();
+        if policy == ExcludedFk::Fail || !unavailable.is_empty() {
+            let (problem, listed) = if unavailable.is_empty() {
+                ("excluded tables", dangling.iter().map(describe).collect())
+            } else {
+                ("tables outside of --schema", unavailable)
+            };
+            panic!(
+                "{} foreign keys reference {}:\n  {}\n\
+                pass `--on-excluded-fk drop` to leave these foreign keys out, or \
+                `--on-excluded-fk include` to copy the referenced tables anyway",
+                listed.len(),
+                problem,
+                listed.join("\n  ")
+            );
+        }
+        for fk in dangling {
+            if kept.insert(fk.foreign_table.to_owned()) {
+                println!(
+                    "-- including {}, which {} references",
+                    fk.foreign_table, fk.table
+                );
+            }
+        }
+    }
+    return rels
+        .into_iter()
+        .filter(|r| kept.contains(&naming.sqlite_name(&r.schema, &r.name)))
+        .collect();
+}
+
+/// Drop views that depend on relations which aren't being copied, and any
+/// usages that point at dropped relations.
+pub fn prune_views(
+    views: &mut HashMap<String, View>,
+    tables: &HashMap<String, super::Table>,
+    usages: Vec<ViewRelUsage>,
+) -> Vec<ViewRelUsage> {
+    // `rel_name` is the dependent view; `view_name` is the relation it uses.
+    loop {
+        let orphaned: Vec<(String, String)> = usages
+            .iter()
+            .filter(|u| views.contains_key(&u.rel_name))
+            .filter(|u| !tables.contains_key(&u.view_name) && !views.contains_key(&u.view_name))
+            .map(|u| (u.rel_name.to_owned(), u.view_name.to_owned()))
+            .collect();
+        if orphaned.is_empty() {
+            break;
+        }
+        for (view, missing) in orphaned {
+            if views.remove(&view).is_some() {
+                println!("-- skipping view {}, which depends on {}", view, missing);
+            }
+        }
+    }
+    // materialized views copied as tables keep their edges, too, so they're
+    // filled after the relations they were computed from
+    let materialized = |name: &String| matches!(tables.get(name), Some(t) if t.materialized);
+    return usages
+        .into_iter()
+        .filter(|u| views.contains_key(&u.rel_name) || materialized(&u.rel_name))
+        .filter(|u| tables.contains_key(&u.view_name) || views.contains_key(&u.view_name))
+        .collect();
+}
+
+#[test]
+fn test_glob_and_regex_patterns() {
+    let glob = Pattern::parse("audit_*").unwrap();
+    assert!(glob.matches("public", "audit_log"));
+    assert!(!glob.matches("public", "my_audit_log"));
+    let qualified = Pattern::parse("staging.*").unwrap();
+    assert!(qualified.matches("staging", "anything"));
+    assert!(!qualified.matches("public", "anything"));
+    let class = Pattern::parse("t[!0-9]?").unwrap();
+    assert!(class.matches("public", "tab"));
+    assert!(!class.matches("public", "t1b"));
+    let re = Pattern::parse("/^stg_\\d+$/").unwrap();
+    assert!(re.matches("public", "stg_20200101"));
+    assert!(!re.matches("public", "stg_x"));
+    assert!(Pattern::parse("/(/").is_err());
+}
+
+#[test]
+fn test_include_then_exclude() {
+    let filter = RelFilter {
+        include_tables: vec![Pattern::parse("_*").unwrap()],
+        exclude_tables: vec![Pattern::parse("_blob").unwrap()],
+        ..Default::default()
+    };
+    let rel = |name: &str, relkind: &str| Rel {
+        oid: 0,
+        schema: "public".to_owned(),
+        name: name.to_owned(),
+        relkind: relkind.to_owned(),
+        approx_n_rows: 0,
+        populated: true,
+        partition_root: None,
+        inherits: false,
+    };
+    assert!(filter.keep(&rel("_file", "table")));
+    assert!(!filter.keep(&rel("_blob", "table")));
+    assert!(!filter.keep(&rel("other", "table")));
+    assert!(filter.keep(&rel("files", "view")));
+    assert!(filter.keep(&rel("_file_stats", "materialized view")));
+}
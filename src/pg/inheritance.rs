@@ -0,0 +1,64 @@
+//! Copy tables that use `INHERITS`, whose parents also return their
+//! children's rows unless they're read with `FROM ONLY`.
+
+use super::{FkeyConstraint, Naming, Rel};
+use std::collections::HashSet;
+
+/// How to copy tables that inherit from other tables.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Inheritance {
+    /// each table holds only its own rows, as with `SELECT ... FROM ONLY`
+    Only,
+    /// parents hold their descendants' rows, too, in the columns they share,
+    /// and the descendants themselves aren't copied
+    Flatten,
+}
+
+pub const INHERITANCE_POLICIES: &[&str] = &["only", "flatten"];
+
+impl Inheritance {
+    pub fn parse(policy: &str) -> Inheritance {
+        match policy {
+            "only" => Inheritance::Only,
+            "flatten" => Inheritance::Flatten,
+            other => panic!("unknown inheritance policy {:?}", other),
+        }
+    }
+}
+
+/// When flattening, drop the tables that inherit from others, along with
+/// their foreign keys and those that reference them. Unlike partitions'
+/// foreign keys, these aren't copies of a parent's, so each is reported.
+pub fn select_children(
+    rels: Vec<Rel>,
+    fkeys: Vec<FkeyConstraint>,
+    policy: Inheritance,
+    naming: &Naming,
+) -> (Vec<Rel>, Vec<FkeyConstraint>) {
+    if policy == Inheritance::Only {
+        return (rels, fkeys);
+    }
+    let (children, kept): (Vec<Rel>, Vec<Rel>) = rels.into_iter().partition(|rel| rel.inherits);
+    let children: HashSet<String> = children
+        .iter()
+        .map(|rel| naming.sqlite_name(&rel.schema, &rel.name))
+        .collect();
+    let fkeys = fkeys
+        .into_iter()
+        .filter(|fk| {
+            let child = if children.contains(&fk.table) {
+                &fk.table
+            } else if children.contains(&fk.foreign_table) {
+                &fk.foreign_table
+            } else {
+                return true;
+            };
+            println!(
+                "-- dropping foreign key {} from {} to {}: {} is flattened into its parent",
+                fk.name, fk.table, fk.foreign_table, child
+            );
+            false
+        })
+        .collect();
+    return (kept, fkeys);
+}
@@ -1,48 +1,77 @@
 use super::{
-    ColInfo, FkeyConstraint, PkeyConstraint, Rel, Table, UniqueConstraint, View, ViewRelUsage,
+    CheckConstraint, ColInfo, FkeyConstraint, Index, Naming, PkeyConstraint, Rel, Table,
+    UniqueConstraint, View, ViewRelUsage,
 };
 use crate::pg::object_types::{get_pg_type_from_name, pretty_relkind};
 use crate::pg::query;
+use crate::quote;
 use std::collections::HashMap;
 
-pub fn get_table_defns(
-    conn: &mut postgres::Client,
-    tables: &mut HashMap<String, Table>,
-    schema: &str,
-) {
-    let table_names: Vec<String> = tables.iter().map(|(name, _)| name.to_owned()).collect();
+pub fn get_table_defns(conn: &mut postgres::Transaction, tables: &mut HashMap<String, Table>) {
+    let mut keys = HashMap::new();
+    for (key, table) in tables.iter() {
+        keys.insert(
+            (table.schema.to_owned(), table.name.to_owned()),
+            key.to_owned(),
+        );
+    }
+    let table_schemas: Vec<String> = keys.keys().map(|(schema, _)| schema.to_owned()).collect();
+    let table_names: Vec<String> = keys.keys().map(|(_, name)| name.to_owned()).collect();
     let cols = query::must_succeed(conn.query(
         include_str!("./queries/table_definitions.sql"),
-        &[&schema, &table_names],
+        &[&table_schemas, &table_names],
     ));
     for row in cols {
+        let table_schema: String = row.get("table_schema");
         let table_name: String = row.get("table_name");
         let column_name: String = row.get("column_name");
         let data_type: String = row.get("udt_name");
         let is_nullable: &str = row.get("is_nullable");
+        let default: Option<String> = row.get("column_default");
+        let is_serial: bool = row.get("is_serial");
+        let last_value: Option<i64> = row.get("sequence_last_value");
+        let precision: Option<i32> = row.get("numeric_precision");
+        let scale: Option<i32> = row.get("numeric_scale");
         let pg_type = get_pg_type_from_name(&data_type).unwrap_or_else(|err| panic!(err));
         let col = ColInfo {
             name: column_name.clone(),
             data_type: pg_type,
             nullable: (is_nullable == "YES"),
+            default,
+            sqlite_default: None, // translated once every table is known
+            serial: is_serial,
+            last_value,
+            rowid_alias: false, // decided once primary keys are known
+            autoincrement: false,
+            precision,
+            scale,
+            numeric: None, // chosen once options are applied
+            datetime: None,
         };
-        let table = tables.get_mut(&table_name).unwrap();
+        let table = tables.get_mut(&keys[&(table_schema, table_name)]).unwrap();
         table.column_order.push(column_name.clone());
         table.columns.insert(column_name, col);
     }
 }
 
 /// WARNING: postgres converts `CAST(thing AS TYPE)` to `thing::TYPE`, which sqlite can't handle.
-pub fn get_view_defns(conn: &mut postgres::Client, views: &mut HashMap<String, View>) {
+pub fn get_view_defns(conn: &mut postgres::Transaction, views: &mut HashMap<String, View>) {
     let oids: Vec<u32> = views.iter().map(|(_, v)| v.oid).collect();
-    let defns = query::must_succeed(conn.query(
-        include_str!("./queries/view_definitions.sql"),
-        &[&oids],
+    // with nothing on the search_path, pg_get_viewdef qualifies every
+    // relation with its schema, so `rename_relations` can find them
+    let search_path = query::must_succeed(conn.query("SHOW search_path", &[]));
+    let search_path: String = search_path[0].get(0);
+    query::must_succeed(conn.query("SELECT set_config('search_path', '', true)", &[]));
+    let defns =
+        query::must_succeed(conn.query(include_str!("./queries/view_definitions.sql"), &[&oids]));
+    query::must_succeed(conn.query(
+        "SELECT set_config('search_path', $1, true)",
+        &[&search_path],
     ));
     for row in defns {
-        let name: String = row.get("name");
+        let oid: u32 = row.get("oid");
         let defn: String = row.get("defn");
-        let view = views.get_mut(&name).unwrap();
+        let view = views.values_mut().find(|v| v.oid == oid).unwrap();
         view.defn = defn;
     }
 }
@@ -62,63 +91,88 @@ pub fn get_view_defns(conn: &mut postgres::Client, views: &mut HashMap<String, V
 //     .collect();
 // }
 
-pub fn list_relations_in_schema(conn: &mut postgres::Client, schema_name: &str) -> Vec<Rel> {
+pub fn list_relations_in_schema(conn: &mut postgres::Transaction, schemas: &[String]) -> Vec<Rel> {
     return query::must_succeed(conn.query(
         include_str!("./queries/list_relations_in_schema.sql"),
-        &[&schema_name],
+        &[&schemas],
     ))
     .iter()
     .map(|row| {
         let oid = row.get("oid");
+        let schema = row.get("schema");
         let name = row.get("name");
         let approx_n_rows = row.get("approx_n_rows");
         let relkind = pretty_relkind(row.get("relkind")).to_owned();
+        let populated = row.get("populated");
+        let partition_root = row.get("partition_root");
+        let inherits = row.get("inherits");
         return Rel {
             oid,
+            schema,
             name,
             relkind,
             approx_n_rows,
+            populated,
+            partition_root,
+            inherits,
         };
     })
     .collect();
 }
 
-// TODO: parametrize with a Vec<str> schema names
 pub(crate) fn get_all_fkey_constraints(
-    conn: &mut postgres::Client,
-    schema: &str,
+    conn: &mut postgres::Transaction,
+    schemas: &[String],
+    naming: &Naming,
 ) -> Vec<FkeyConstraint> {
     return query::must_succeed(conn.query(
         include_str!("./queries/all_fk_constraints.sql"),
-        &[&schema],
+        &[&schemas],
     ))
     .iter()
     .map(|row| {
-        let table = row.get("table_name");
+        let schema: String = row.get("table_schema");
+        let table: String = row.get("table_name");
         let col = row.get("columns");
         let constraint = row.get("constraint_name");
-        let foreign_table = row.get("foreign_table_name");
+        let foreign_schema: String = row.get("foreign_table_schema");
+        let foreign_table: String = row.get("foreign_table_name");
         let foreign_columns = row.get("foreign_columns");
+        let inherited = row.get("is_inherited");
         return FkeyConstraint {
-            table,
+            on_delete: row.get("on_delete"),
+            on_update: row.get("on_update"),
+            match_type: row.get("match_type"),
+            deferrable: row.get("is_deferrable"),
+            initially_deferred: row.get("initially_deferred"),
+            table: naming.sqlite_name(&schema, &table),
             columns: col,
             name: constraint,
-            foreign_table,
+            foreign_table: naming.sqlite_name(&foreign_schema, &foreign_table),
+            references: quote::sqlite(&naming.local_name(&foreign_schema, &foreign_table)),
             foreign_columns,
+            schema,
+            foreign_schema,
+            inherited,
         };
     })
     .collect();
 }
 
-pub fn get_all_pkey_constraints(conn: &mut postgres::Client, schema: &str) -> Vec<PkeyConstraint> {
+pub fn get_all_pkey_constraints(
+    conn: &mut postgres::Transaction,
+    schemas: &[String],
+    naming: &Naming,
+) -> Vec<PkeyConstraint> {
     return query::must_succeed(conn.query(
         include_str!("./queries/all_pk_constraints.sql"),
-        &[&schema],
+        &[&schemas],
     ))
     .iter()
     .map(|row| {
         let name = row.get("constraint_name");
-        let table = row.get("table_name");
+        let schema: String = row.get("table_schema");
+        let table = naming.sqlite_name(&schema, row.get("table_name"));
         let columns = row.get("columns");
         return PkeyConstraint {
             name,
@@ -130,17 +184,19 @@ pub fn get_all_pkey_constraints(conn: &mut postgres::Client, schema: &str) -> Ve
 }
 
 pub fn get_all_unique_constraints(
-    conn: &mut postgres::Client,
-    schema: &str,
+    conn: &mut postgres::Transaction,
+    schemas: &[String],
+    naming: &Naming,
 ) -> Vec<UniqueConstraint> {
     return query::must_succeed(conn.query(
         include_str!("./queries/all_unique_constraints.sql"),
-        &[&schema],
+        &[&schemas],
     ))
     .iter()
     .map(|row| {
         let name = row.get("constraint_name");
-        let table = row.get("table_name");
+        let schema: String = row.get("table_schema");
+        let table = naming.sqlite_name(&schema, row.get("table_name"));
         let columns = row.get("columns");
         return UniqueConstraint {
             name,
@@ -151,27 +207,88 @@ pub fn get_all_unique_constraints(
     .collect();
 }
 
-pub(crate) fn get_view_refs(conn: &mut postgres::Client, schema: &str) -> Vec<ViewRelUsage> {
-    return query::must_succeed(conn.query(
-        include_str!("./queries/view_usage.sql"),
-        &[&schema],
-    ))
+pub fn get_all_check_constraints(
+    conn: &mut postgres::Transaction,
+    schemas: &[String],
+    naming: &Naming,
+) -> Vec<CheckConstraint> {
+    return query::must_succeed(
+        conn.query(include_str!("./queries/check_constraints.sql"), &[&schemas]),
+    )
     .iter()
     .map(|row| {
-        let view_oid: u32 = row.get("source_oid");
-        let view_name = row.get("source_table");
-        let rel_name = row.get("dependent_rel");
-        let rel_oid: u32 = row.get("dependent_oid");
-        return ViewRelUsage {
-            view_oid,
-            view_name,
-            rel_name,
-            rel_oid,
+        let name = row.get("constraint_name");
+        let schema: String = row.get("table_schema");
+        let table = naming.sqlite_name(&schema, row.get("table_name"));
+        let defn = row.get("defn");
+        let expr = row.get("expr"); // translated once the table's columns are known
+        return CheckConstraint {
+            name,
+            table,
+            defn,
+            expr,
+            untranslatable: None,
         };
     })
     .collect();
 }
 
+pub fn get_all_indexes(
+    conn: &mut postgres::Transaction,
+    schemas: &[String],
+    naming: &Naming,
+) -> Vec<Index> {
+    return query::must_succeed(conn.query(include_str!("./queries/indexes.sql"), &[&schemas]))
+        .iter()
+        .map(|row| {
+            let schema: String = row.get("table_schema");
+            let table_name: String = row.get("table_name");
+            return Index {
+                name: naming.sqlite_name(&schema, row.get("index_name")),
+                ident: naming.sqlite_ident(&schema, row.get("index_name")),
+                table: naming.sqlite_name(&schema, &table_name),
+                local_table: quote::sqlite(&naming.local_name(&schema, &table_name)),
+                method: row.get("method"),
+                unique: row.get("is_unique"),
+                defn: row.get("defn"),
+                keys: row.get("keys"), // translated once the table's columns are known
+                columns: row.get("key_columns"),
+                descending: row.get("descending"),
+                predicate: row.get("predicate"),
+            };
+        })
+        .collect();
+}
+
+pub(crate) fn get_view_refs(
+    conn: &mut postgres::Transaction,
+    schemas: &[String],
+    naming: &Naming,
+) -> Vec<ViewRelUsage> {
+    return query::must_succeed(conn.query(include_str!("./queries/view_usage.sql"), &[&schemas]))
+        .iter()
+        .map(|row| {
+            let view_oid: u32 = row.get("source_oid");
+            let source_schema: String = row.get("source_schema");
+            let source_table: &str = row.get("source_table");
+            // a relation in a schema that isn't copied matches no table or view
+            let view_name = match schemas.contains(&source_schema) {
+                true => naming.sqlite_name(&source_schema, source_table),
+                false => format!("{}.{}", source_schema, source_table),
+            };
+            let rel_name =
+                naming.sqlite_name(row.get("dependent_schema"), row.get("dependent_rel"));
+            let rel_oid: u32 = row.get("dependent_oid");
+            return ViewRelUsage {
+                view_oid,
+                view_name,
+                rel_name,
+                rel_oid,
+            };
+        })
+        .collect();
+}
+
 // fn list_view_dependencies(conn: &mut postgres::Client, schema: &str) -> Vec<ViewRelUsage> {
 //     return query::must_succeed(conn.query(
 //         "
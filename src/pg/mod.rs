@@ -3,30 +3,59 @@
 // might include multiple, parallel edges like "table A columng alpha depnds on
 // (references) table B column beta; table A column x depends on table B column y"
 
+use crate::quote;
 use petgraph::graph::Graph;
-use petgraph::{self, algo::toposort};
+use petgraph::visit::EdgeRef;
+use petgraph::{self, algo::tarjan_scc};
 use postgres::{self, RowIter, Transaction};
 use postgres_types::Type as PgType;
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     convert::TryInto,
-    fmt,
     intrinsics::transmute,
     u32,
     vec::Vec,
 };
+mod array;
+mod conninfo;
+mod datetime;
+mod ddl;
+mod expr;
+mod extract;
+mod filter;
+mod inheritance;
 mod introspection;
+mod naming;
+mod numeric;
 mod object_types;
+mod parallel;
+mod partition;
 mod query;
+mod tls;
 mod validate;
-use fallible_iterator::FallibleIterator;
 
+pub use array::{Arrays, ARRAY_POLICIES};
+pub use conninfo::ConnParams;
+pub use datetime::{Datetime, Timestamptz, DATETIME_POLICIES, TIMESTAMPTZ_POLICIES};
+pub use expr::{Untranslatable, UNTRANSLATABLE_POLICIES};
+pub use extract::{dump_table, Extractor, EXTRACTORS};
+pub use filter::{
+    ExcludedFk, ForeignTables, MaterializedViews, Pattern, RelFilter, EXCLUDED_FK_POLICIES,
+    FOREIGN_TABLE_POLICIES, MATERIALIZED_VIEW_POLICIES,
+};
+pub use inheritance::{Inheritance, INHERITANCE_POLICIES};
 use introspection::{
-    get_all_fkey_constraints, get_all_pkey_constraints, get_all_unique_constraints,
-    get_table_defns, get_view_defns, get_view_refs, list_relations_in_schema,
+    get_all_check_constraints, get_all_fkey_constraints, get_all_indexes, get_all_pkey_constraints,
+    get_all_unique_constraints, get_table_defns, get_view_defns, get_view_refs,
+    list_relations_in_schema,
 };
-use object_types::{sqlite_type_from_pg_type, translate_row};
-pub use query::connect;
+pub use naming::{Naming, NAMING_POLICIES};
+pub use numeric::{Numeric, NumericColumn, NUMERIC_POLICIES};
+pub use object_types::{Binder, CellSource};
+pub use parallel::transfer_tables;
+pub use partition::{Partitions, PARTITION_POLICIES};
+pub use query::{begin_snapshot, connect, export_snapshot, set_time_zone, ConnectError};
+pub use tls::SSL_MODES;
 
 // TODO: constraint enum::{check, fkey, unique, pkey}
 // TODO: implement rustqlite::ToSql as ToSqlite
@@ -34,34 +63,22 @@ pub use query::connect;
 #[derive(Debug, Clone)]
 pub struct Table {
     oid: u32,
+    schema: String,
     name: String,
+    sqlite_name: String,
+    sqlite_ident: String, // `sqlite_name`, quoted for SQL
     column_order: Vec<String>,
     columns: HashMap<String, ColInfo>,
     pk_constraints: Vec<PkeyConstraint>,
     fkey_constraints: Vec<FkeyConstraint>,
     unique_constraints: Vec<UniqueConstraint>,
+    check_constraints: Vec<CheckConstraint>,
+    indexes: Vec<Index>,
     approx_n_rows: i64,
-}
-
-fn create_sqlite_table_stmt(t: Table) -> String {
-    return format!("{}", t);
-    // let cols: Vec<String> = t
-    //     .column_order
-    //     .iter()
-    //     .map(|col_name| t.columns.get(col_name).unwrap())
-    //     .map(|col| format!("{}", col))
-    //     .collect();
-    // return format!(
-    //     "CREATE TABLE {} (\n  {}\n{});\n  -- ~ {} rows\n",
-    //     &t.name,
-    //     cols.join("\n  , "),
-    //     &t.fkey_constraints
-    //         .iter()
-    //         .map(|fk| format!("{}", fk))
-    //         .collect::<Vec<String>>()
-    //         .join("\n  ,"),
-    //     &t.approx_n_rows
-    // );
+    materialized: bool,               // a snapshot of a materialized view
+    partition_column: Option<String>, // records which partition each row came from
+    include_children: bool, // read without `ONLY`, so partitions' or children's rows come, too
+    unnest: Option<String>, // an array column of the relation, one row per element; see `array`
 }
 
 #[derive(Debug, Clone)]
@@ -79,20 +96,52 @@ pub struct UniqueConstraint {
 #[derive(Debug, Clone)]
 pub struct FkeyConstraint {
     name: String,
+    schema: String,
     table: String,
     columns: Vec<String>,
+    foreign_schema: String,
     foreign_table: String,        // could be Vec<String>
     foreign_columns: Vec<String>, // could be Vec<String>
+    references: String,           // the foreign table's name within its own database, quoted
+    inherited: bool,              // copied by postgres from a partitioned table's foreign key
+    on_delete: Option<String>,    // e.g. `CASCADE`; `None` for `NO ACTION`
+    on_update: Option<String>,
+    match_type: Option<String>, // `FULL` or `PARTIAL`; `None` for `SIMPLE`
+    deferrable: bool,
+    initially_deferred: bool,
 }
-#[derive(Debug)]
-struct CheckConstraint {
-    oid: u32,
-    defn: String,
+#[derive(Debug, Clone)]
+pub struct CheckConstraint {
+    name: String,
+    table: String,
+    defn: String, // as postgres prints it, e.g. `CHECK ((price > (0)::numeric))`
+    expr: String, // translated to SQLite
+    untranslatable: Option<String>, // why SQLite rejected `expr`, if it did
+}
+/// Access methods whose indexes SQLite's b-trees can stand in for.
+const INDEX_METHODS: &[&str] = &["btree", "hash"];
+
+#[derive(Debug, Clone)]
+pub struct Index {
+    name: String,        // within SQLite, like a table's `sqlite_name`
+    ident: String,       // `name`, quoted for SQL
+    table: String,       // the indexed table's `sqlite_name`
+    local_table: String, // the indexed table's name within its own database, quoted
+    method: String,      // postgres' access method, e.g. `btree` or `gin`
+    unique: bool,
+    defn: String,                 // as postgres prints it
+    keys: Vec<String>,            // columns or expressions, translated to SQLite
+    columns: Vec<Option<String>>, // each key's column, unless it's an expression
+    descending: Vec<bool>,        // for each key
+    predicate: Option<String>,    // of a partial index, translated to SQLite
 }
 #[derive(Debug)]
 pub struct View {
     pub oid: u32,
+    pub schema: String,
     pub name: String,
+    pub sqlite_name: String,
+    pub sqlite_ident: String,
     pub defn: String,
     // materialized: bool?
 }
@@ -106,12 +155,13 @@ pub struct ViewRelUsage {
 
 #[derive(Debug)]
 pub struct SchemaInformation {
-    pub name: String,
+    pub schemas: Vec<String>,
+    pub naming: Naming,
     // -- nodes --
     pub tables: HashMap<String, Table>,
     pub views: HashMap<String, View>,
     pub order: Vec<String>,
-    // check_constraints,
+    pub cycles: Vec<Cycle>,
     // not_null_constraints,
     // -- edges --
     fkey_constraints: HashMap<String, FkeyConstraint>,
@@ -119,16 +169,44 @@ pub struct SchemaInformation {
     // -- ??? ---
     // extensions (specifically, for translating postGIS -> spatialite)
     // relations, // the namespace tables+views inhabit ?
-    // sequences  // this one's going to be difficult to replicate, due to sqlite's `rowid` trick
-    // see https://www.sqlitetutorial.net/sqlite-autoincrement/
     dependency_graph: Graph<Node, Edge>,
 }
 
+/// Choices about which relations to copy and how to name them in SQLite.
+#[derive(Debug)]
+pub struct SchemaOptions {
+    pub naming: Naming,
+    pub filter: RelFilter,
+    pub on_excluded_fk: ExcludedFk,
+    pub on_untranslatable_check: Untranslatable,
+    /// declare rowid aliases `AUTOINCREMENT`, so ids are never reused
+    pub autoincrement: bool,
+    pub materialized_views: MaterializedViews,
+    pub partitions: Partitions,
+    /// with `Partitions::Merge`, a column to add to merged tables, naming
+    /// the partition each row came from
+    pub partition_column: Option<String>,
+    pub inheritance: Inheritance,
+    pub foreign_tables: ForeignTables,
+    /// how to store NUMERIC columns, unless one of `numeric_columns` says otherwise
+    pub numeric: Numeric,
+    pub numeric_columns: Vec<NumericColumn>,
+    /// how to store DATE, TIMESTAMP, and TIMESTAMPTZ columns
+    pub datetime: Datetime,
+    /// with `Datetime::Text`, whether TIMESTAMPTZ is written in UTC
+    pub timestamptz: Timestamptz,
+    pub arrays: Arrays,
+}
+
 pub struct Rel {
     oid: u32,
+    schema: String,
     name: String,
     relkind: String,
     approx_n_rows: i64,
+    populated: bool,
+    partition_root: Option<u32>, // the partitioned table at the top of a partition's tree
+    inherits: bool,              // from another table, with `INHERITS`
 }
 
 #[derive(Debug, Clone)]
@@ -139,104 +217,439 @@ pub struct Node {
 #[derive(Debug)]
 pub struct Edge {
     type_: String,
+    name: String, // the foreign key's, or the view's that uses a relation
 }
 
-pub fn rel_order(g: &Graph<Node, Edge>) -> Vec<String> {
-    // TODO: rename to "rel_order": not just tables
-    let sorted = toposort(g, None);
-    match sorted {
-        Ok(mut r) => {
-            r.reverse();
-            return r
-                .iter()
-                // .filter(|idx| (&(g[**idx]).type_ == "t"))
-                .map(|idx| (&(g[*idx]).name).to_owned())
-                .collect();
+/// Tables whose foreign keys reference one another in a cycle, so no order
+/// loads each of them after the tables it references. A table with a foreign
+/// key to itself is a cycle of one.
+#[derive(Debug, Clone)]
+pub struct Cycle {
+    pub tables: Vec<String>,
+    pub fkeys: Vec<String>, // `name (table -> foreign table)`
+}
+
+/// Relations in an order that puts each after the relations it depends on,
+/// and the cycles that make that impossible for some tables. The tables in
+/// a cycle are adjacent in the order, and are loaded with foreign keys
+/// unenforced, like every other table, then checked once they're all in.
+pub fn rel_order(g: &Graph<Node, Edge>) -> (Vec<String>, Vec<Cycle>) {
+    let mut order = vec![];
+    let mut cycles = vec![];
+    // tarjan_scc finds the strongly connected components in reverse
+    // topological order, i.e. dependencies first
+    for component in tarjan_scc(g) {
+        let mut names: Vec<String> = component
+            .iter()
+            .map(|idx| g[*idx].name.to_owned())
+            .collect();
+        names.sort();
+        let mut fkeys: Vec<String> = g
+            .edge_references()
+            .filter(|e| component.contains(&e.source()) && component.contains(&e.target()))
+            .map(|e| {
+                format!(
+                    "{} ({} -> {})",
+                    e.weight().name,
+                    g[e.source()].name,
+                    g[e.target()].name
+                )
+            })
+            .collect();
+        fkeys.sort();
+        if !fkeys.is_empty() {
+            cycles.push(Cycle {
+                tables: names.clone(),
+                fkeys,
+            });
         }
-        Err(c) => panic!("{:?}", c),
+        order.extend(names);
     }
+    return (order, cycles);
 }
 
 impl SchemaInformation {
-    pub fn new(conn: &mut postgres::Client, schema: &str) -> SchemaInformation {
-        let rels = list_relations_in_schema(conn, schema);
+    pub fn new(
+        conn: &mut postgres::Transaction,
+        schemas: &[String],
+        options: SchemaOptions,
+    ) -> SchemaInformation {
+        let naming = options.naming;
+        let (rels, all_fkeys) = partition::select_partitions(
+            list_relations_in_schema(conn, schemas),
+            get_all_fkey_constraints(conn, schemas, &naming),
+            options.partitions,
+            &naming,
+        );
+        let (rels, all_fkeys) =
+            inheritance::select_children(rels, all_fkeys, options.inheritance, &naming);
+        let flatten = options.inheritance == Inheritance::Flatten;
+        let rels = filter::select_relations(
+            rels,
+            &all_fkeys,
+            &options.filter,
+            options.on_excluded_fk,
+            &naming,
+        );
         let mut tables = HashMap::new();
         let mut views = HashMap::new();
 
-        fn add_table(rel: Rel, tables: &mut HashMap<String, Table>) {
+        fn add_table(
+            rel: Rel,
+            tables: &mut HashMap<String, Table>,
+            naming: &Naming,
+            flatten: bool,
+        ) {
             let table = Table {
                 oid: rel.oid,
+                include_children: flatten || rel.relkind == "partitioned table",
+                unnest: None,
+                materialized: rel.relkind == "materialized view",
+                partition_column: None, // added once columns are known
+                sqlite_name: naming.sqlite_name(&rel.schema, &rel.name),
+                sqlite_ident: naming.sqlite_ident(&rel.schema, &rel.name),
+                schema: rel.schema,
                 name: rel.name.to_owned(),
                 approx_n_rows: rel.approx_n_rows,
                 column_order: vec![],
                 fkey_constraints: vec![],
                 unique_constraints: vec![],
                 pk_constraints: vec![],
+                check_constraints: vec![],
+                indexes: vec![],
                 columns: HashMap::new(), // pupulated later
             };
-            if tables.contains_key(&table.name) {
-                panic!("duplicate table {}", table.name)
+            if tables.contains_key(&table.sqlite_name) {
+                panic!("duplicate table {}", table.sqlite_name)
             }
-            tables.insert(table.name.to_owned(), table);
+            tables.insert(table.sqlite_name.to_owned(), table);
         }
-        fn add_view(rel: Rel, views: &mut HashMap<String, View>, tables: &HashMap<String, Table>) {
+        fn add_view(
+            rel: Rel,
+            views: &mut HashMap<String, View>,
+            tables: &HashMap<String, Table>,
+            naming: &Naming,
+        ) {
             let view = View {
                 oid: rel.oid,
+                sqlite_name: naming.sqlite_name(&rel.schema, &rel.name),
+                sqlite_ident: naming.sqlite_ident(&rel.schema, &rel.name),
+                schema: rel.schema,
                 name: rel.name.to_owned(),
                 defn: "".to_owned(),
             };
-            if views.contains_key(&view.name) {
-                panic!("duplicate view {}", view.name);
+            if views.contains_key(&view.sqlite_name) {
+                panic!("duplicate view {}", view.sqlite_name);
             }
-            if tables.contains_key(&view.name) {
-                panic!("view {} conflicts with table {}", view.name, view.name);
+            if tables.contains_key(&view.sqlite_name) {
+                panic!(
+                    "view {} conflicts with table {}",
+                    view.sqlite_name, view.sqlite_name
+                );
             }
-            views.insert(view.name.to_owned(), view);
+            views.insert(view.sqlite_name.to_owned(), view);
         }
 
+        let partitioned: Vec<u32> = rels
+            .iter()
+            .filter(|rel| rel.relkind == "partitioned table")
+            .map(|rel| rel.oid)
+            .collect();
         for rel in rels {
             match rel.relkind.as_str() {
-                "table" => add_table(rel, &mut tables),
-                "view" => add_view(rel, &mut views, &tables),
+                "table" => add_table(rel, &mut tables, &naming, flatten),
+                "view" => add_view(rel, &mut views, &tables, &naming),
+                "partitioned table" => add_table(rel, &mut tables, &naming, flatten),
+                "foreign table" => match options.foreign_tables {
+                    ForeignTables::Skip => println!(
+                        "-- skipping foreign table {}.{}; pass `--foreign-tables copy` to copy its rows",
+                        rel.schema, rel.name
+                    ),
+                    ForeignTables::Copy => add_table(rel, &mut tables, &naming, flatten),
+                },
+                "materialized view" => match options.materialized_views {
+                    MaterializedViews::Table if !rel.populated => println!(
+                        "-- skipping materialized view {}.{}: it hasn't been populated",
+                        rel.schema, rel.name
+                    ),
+                    MaterializedViews::Table => add_table(rel, &mut tables, &naming, flatten),
+                    MaterializedViews::View => add_view(rel, &mut views, &tables, &naming),
+                },
                 unknown => panic!("unrecognized rel type {}", unknown),
             }
         }
 
-        get_table_defns(conn, &mut tables, schema);
+        get_table_defns(conn, &mut tables);
         get_view_defns(conn, &mut views);
+        if let Some(column) = &options.partition_column {
+            for table in tables.values_mut() {
+                if partitioned.contains(&table.oid) {
+                    table.add_partition_column(column);
+                }
+            }
+        }
 
         let mut fkey_constraints = HashMap::new();
-        for fk in get_all_fkey_constraints(conn, schema) {
-            if fkey_constraints.contains_key(&fk.name) {
-                panic!("duplicate foreign key name {}", fk.name); //
+        for fk in all_fkeys {
+            // constraint names are only unique per table
+            let key = format!("{}.{}", fk.table, fk.name);
+            if fkey_constraints.contains_key(&key) {
+                panic!("duplicate foreign key name {}", key); //
+            }
+            if !tables.contains_key(&fk.foreign_table) {
+                if tables.contains_key(&fk.table) {
+                    println!(
+                        "-- dropping foreign key {} from {} to excluded table {}",
+                        fk.name, fk.table, fk.foreign_table
+                    );
+                }
+                continue;
+            }
+            let tbl = match tables.get_mut(&fk.table) {
+                Some(tbl) => tbl,
+                None => continue, // the referencing table was excluded
+            };
+            if naming.can_reference(&fk.schema, &fk.foreign_schema) {
+                tbl.fkey_constraints.push(fk.clone());
+            } else {
+                println!(
+                    "-- omitting foreign key {} from {} to {}: it would span attached databases",
+                    fk.name, fk.table, fk.foreign_table
+                );
             }
-            let tbl = tables.get_mut(&fk.table).unwrap();
-            tbl.fkey_constraints.push(fk.clone());
-            fkey_constraints.insert(fk.name.to_owned(), fk);
+            fkey_constraints.insert(key, fk);
         }
-        for pk in get_all_pkey_constraints(conn, schema) {
-            let tbl = tables.get_mut(&pk.table).unwrap();
-            tbl.pk_constraints.push(pk);
+        for pk in get_all_pkey_constraints(conn, schemas, &naming) {
+            if let Some(tbl) = tables.get_mut(&pk.table) {
+                tbl.pk_constraints.push(pk);
+            }
             // TODO: validate pk name uniqueness?
         }
-        for uq in get_all_unique_constraints(conn, schema) {
-            let tbl = tables.get_mut(&uq.table).unwrap();
-            tbl.unique_constraints.push(uq);
+        for uq in get_all_unique_constraints(conn, schemas, &naming) {
+            if let Some(tbl) = tables.get_mut(&uq.table) {
+                tbl.unique_constraints.push(uq);
+            }
         }
-        let view_rel_usage = get_view_refs(conn, schema);
+        if options.arrays == Arrays::Table {
+            let mut parents: Vec<String> = tables.keys().cloned().collect();
+            parents.sort(); // so the report comes out in a stable order
+            for parent in parents {
+                let children = array::split_columns(tables.get_mut(&parent).unwrap(), &naming);
+                for child in children {
+                    if tables.contains_key(&child.sqlite_name) {
+                        panic!(
+                            "{} would hold the elements of an array column of {}, but it's \
+                            already a table; pass `--arrays json`",
+                            child.sqlite_name, parent
+                        );
+                    }
+                    for fk in &child.fkey_constraints {
+                        fkey_constraints.insert(format!("{}.{}", fk.table, fk.name), fk.clone());
+                    }
+                    tables.insert(child.sqlite_name.to_owned(), child);
+                }
+            }
+        }
+        for table in tables.values_mut() {
+            table.alias_rowid(options.autoincrement);
+        }
+        let mut table_names: Vec<String> = tables.keys().cloned().collect();
+        table_names.sort(); // so the report comes out in a stable order
+        for name in table_names {
+            let table = tables.get_mut(&name).unwrap();
+            for column in &table.column_order {
+                let col = &table.columns[column];
+                let numeric = match col.data_type {
+                    PgType::NUMERIC => Some(numeric::choose(
+                        table,
+                        col,
+                        options.numeric,
+                        &options.numeric_columns,
+                    )),
+                    _ => None,
+                };
+                let datetime =
+                    datetime::choose(&col.data_type, options.datetime, options.timestamptz);
+                let col = table.columns.get_mut(column).unwrap();
+                col.numeric = numeric;
+                col.datetime = datetime;
+            }
+            for column in &table.column_order {
+                let col = table.columns.get_mut(column).unwrap();
+                if col.rowid_alias {
+                    continue; // SQLite numbers rows itself
+                }
+                let default = match &col.default {
+                    Some(default) => default,
+                    None => continue,
+                };
+                let translated =
+                    expr::default_to_sqlite(default).and_then(|sqlite_default| {
+                        match (col.numeric, col.datetime) {
+                            (Some(storage), _) => {
+                                numeric::default_to_sqlite(&sqlite_default, storage, col.scale)
+                            }
+                            (None, Some(storage)) => datetime::default_to_sqlite(
+                                &sqlite_default,
+                                &col.data_type,
+                                storage,
+                            ),
+                            (None, None) if array::element_type(&col.data_type).is_some() => {
+                                array::default_to_sqlite(&sqlite_default)
+                            }
+                            (None, None) => Ok(sqlite_default),
+                        }
+                    });
+                match translated {
+                    Ok(sqlite_default) => col.sqlite_default = Some(sqlite_default),
+                    Err(reason) => println!(
+                        "-- dropping default {} of {}.{}: {}",
+                        default, name, column, reason
+                    ),
+                }
+            }
+        }
+        for mut check in get_all_check_constraints(conn, schemas, &naming) {
+            let tbl = match tables.get_mut(&check.table) {
+                Some(tbl) => tbl,
+                None => continue,
+            };
+            if let Some(approximation) = expr::approximation(&check.expr) {
+                println!(
+                    "-- approximating check constraint {} on {}: {}",
+                    check.name, check.table, approximation
+                );
+            }
+            let translated = tbl.expr_to_sqlite(&check.expr).and_then(|sqlite| {
+                expr::validate(&sqlite, &tbl.column_order)?;
+                return Ok(sqlite);
+            });
+            match translated {
+                Ok(sqlite) => check.expr = sqlite,
+                Err(reason) => match options.on_untranslatable_check {
+                    Untranslatable::Drop => {
+                        println!(
+                            "-- dropping check constraint {} on {}: {}",
+                            check.name, check.table, reason
+                        );
+                        continue;
+                    }
+                    Untranslatable::Comment => {
+                        println!(
+                            "-- commenting out check constraint {} on {}: {}",
+                            check.name, check.table, reason
+                        );
+                        check.untranslatable = Some(reason);
+                    }
+                },
+            }
+            tbl.check_constraints.push(check);
+        }
+        for mut index in get_all_indexes(conn, schemas, &naming) {
+            let tbl = match tables.get_mut(&index.table) {
+                Some(tbl) => tbl,
+                None => continue,
+            };
+            if !INDEX_METHODS.contains(&index.method.as_str()) {
+                println!(
+                    "-- skipping index {} on {}: SQLite has no {} indexes",
+                    index.name, index.table, index.method
+                );
+                continue;
+            }
+            let approximation = index
+                .keys
+                .iter()
+                .chain(&index.predicate)
+                .find_map(|e| expr::approximation(e));
+            if let Some(approximation) = approximation {
+                println!(
+                    "-- approximating index {} on {}: {}",
+                    index.name, index.table, approximation
+                );
+            }
+            let keys: Result<Vec<String>, String> = index
+                .keys
+                .iter()
+                .zip(&index.columns)
+                .map(|(key, column)| match column {
+                    Some(column) => Ok(quote::sqlite(column)),
+                    None => tbl.expr_to_sqlite(key),
+                })
+                .collect();
+            let predicate = index.predicate.as_deref().map(|p| tbl.expr_to_sqlite(p));
+            let translated = keys.and_then(|keys| {
+                index.keys = keys;
+                index.predicate = predicate.transpose()?;
+                let keys = index.sqlite_keys();
+                return expr::validate_index(&keys, index.predicate.as_deref(), &tbl.column_order);
+            });
+            if let Err(reason) = translated {
+                println!(
+                    "-- skipping index {} on {}: {}\n--   {}",
+                    index.name, index.table, reason, index.defn
+                );
+                continue;
+            }
+            tbl.indexes.push(index);
+        }
+        // `(schema, name)` of each relation that's copied
+        let copied: HashSet<(String, String)> = tables
+            .values()
+            .filter(|t| t.unnest.is_none())
+            .map(|t| (t.schema.to_owned(), t.name.to_owned()))
+            .chain(
+                views
+                    .values()
+                    .map(|v| (v.schema.to_owned(), v.name.to_owned())),
+            )
+            .collect();
+        views.retain(|_, view| {
+            let defn = expr::rename_relations(&view.defn, |schema, name| {
+                let key = (schema.to_owned(), name.to_owned());
+                if !copied.contains(&key) {
+                    return Ok(None);
+                }
+                if !naming.can_reference(&view.schema, schema) {
+                    return Err(format!(
+                        "it uses {}, in another attached database",
+                        naming.sqlite_name(schema, name)
+                    ));
+                }
+                return Ok(Some(quote::sqlite(&naming.local_name(schema, name))));
+            });
+            match defn {
+                Ok(defn) => view.defn = defn,
+                Err(reason) => {
+                    println!("-- skipping view {}: {}", view.sqlite_name, reason);
+                    return false;
+                }
+            }
+            return true;
+        });
+        let view_rel_usage =
+            filter::prune_views(&mut views, &tables, get_view_refs(conn, schemas, &naming));
         let dependency_graph =
             to_dependency_graph(&tables, &views, &view_rel_usage, &fkey_constraints);
-        let table_order = rel_order(&dependency_graph);
-        to_dependency_graph(&tables, &views, &view_rel_usage, &fkey_constraints);
+        let (table_order, cycles) = rel_order(&dependency_graph);
+        for cycle in &cycles {
+            println!(
+                "-- foreign keys form a cycle among {}: {}; checking them once the rows are loaded",
+                cycle.tables.join(", "),
+                cycle.fkeys.join(", ")
+            );
+        }
 
         return SchemaInformation {
-            name: schema.to_owned(),
+            schemas: schemas.to_vec(),
+            naming,
             tables,
             views,
             fkey_constraints,
             view_rel_usage,
             dependency_graph,
             order: table_order, // this gets filled in later
+            cycles,
         };
     }
     fn validate(&self) -> Result<(), String> {
@@ -272,13 +685,85 @@ impl SchemaInformation {
             .map(|t| {
                 // let tbl: = self.tables.get(t);
                 match self.tables.get(t) {
-                    Some(x) => create_sqlite_table_stmt(x.clone()),
+                    Some(x) => ddl::create_table(x),
                     _ => panic!("unable to find table '{}' in {:?}", t, self.tables),
                 }
             })
             .collect();
         return tables.join("\n");
     }
+    /// Indexes are created once the rows are in, which is quicker than
+    /// updating them row by row.
+    pub fn create_index_statements(&self) -> String {
+        let indexes: Vec<String> = self
+            .order
+            .iter()
+            .filter_map(|t| self.tables.get(t))
+            .flat_map(|t| t.indexes.iter())
+            .map(ddl::create_index)
+            .collect();
+        return indexes.join("\n");
+    }
+    /// Continue each `AUTOINCREMENT` table's numbering from its postgres
+    /// sequence. SQLite keeps the largest id it has handed out for each such
+    /// table in its database's `sqlite_sequence`.
+    pub fn sequence_statements(&self) -> String {
+        let mut statements = vec![];
+        for table in self.order.iter().filter_map(|t| self.tables.get(t)) {
+            let col = match table.columns.values().find(|col| col.autoincrement) {
+                Some(col) => col,
+                None => continue,
+            };
+            let last_value = match col.last_value {
+                Some(last_value) => last_value,
+                None => continue, // the sequence was never used
+            };
+            let sequences = self.naming.in_database(&table.schema, "sqlite_sequence");
+            let name = quote::literal(&self.naming.local_name(&table.schema, &table.name));
+            statements.push(format!(
+                "UPDATE {0} SET seq = max(seq, {1}) WHERE name = {2};\n\
+                INSERT INTO {0} (name, seq) SELECT {2}, {1}\n  \
+                WHERE NOT EXISTS (SELECT 1 FROM {0} WHERE name = {2});",
+                sequences, last_value, name
+            ));
+        }
+        return statements.join("\n");
+    }
+    /// `PRAGMA foreign_key_check` for each table in a cycle, whose rows
+    /// couldn't all be loaded after the rows they reference.
+    pub fn foreign_key_checks(&self) -> Vec<String> {
+        return self
+            .cycles
+            .iter()
+            .flat_map(|cycle| cycle.tables.iter())
+            .filter_map(|t| self.tables.get(t))
+            .map(|t| {
+                format!(
+                    "PRAGMA {}({})",
+                    self.naming.in_database(&t.schema, "foreign_key_check"),
+                    quote::sqlite(&self.naming.local_name(&t.schema, &t.name))
+                )
+            })
+            .collect();
+    }
+    /// `(schema, path)` of the databases to attach alongside `dest`, if any.
+    pub fn attachments(&self, dest: &str) -> Vec<(String, String)> {
+        return self.naming.attachments(&self.schemas, dest);
+    }
+    pub fn attach_statements(&self, dest: &str) -> String {
+        let attachments: Vec<String> = self
+            .attachments(dest)
+            .iter()
+            .map(|(schema, path)| {
+                format!(
+                    "ATTACH DATABASE {} AS {};",
+                    quote::literal(path),
+                    quote::sqlite(schema)
+                )
+            })
+            .collect();
+        return attachments.join("\n");
+    }
     pub fn create_view_statements(&self) -> String {
         let views: Vec<String> = self
             .order
@@ -286,7 +771,7 @@ impl SchemaInformation {
             .filter(|name| self.views.contains_key(*name))
             .map(|name| {
                 let view = self.views.get(name).unwrap();
-                return format!("CREATE VIEW {} AS\n{}\n", view.name, view.defn);
+                return format!("CREATE VIEW {} AS\n{}\n", view.sqlite_ident, view.defn);
             })
             .collect();
         return views.join("\n");
@@ -301,15 +786,22 @@ pub fn to_dependency_graph(
 ) -> Graph<Node, Edge> {
     let mut names = HashMap::new();
     let mut deps = Graph::new();
+    // in a stable order, so relations come out in the same order every time
+    let mut table_names: Vec<&String> = tables.keys().collect();
+    table_names.sort();
+    let mut view_names: Vec<&String> = views.keys().collect();
+    view_names.sort();
+    let mut fkeys: Vec<(&String, &FkeyConstraint)> = fkey_constraints.iter().collect();
+    fkeys.sort_by_key(|(key, _)| *key);
 
-    for (name, _) in tables {
+    for name in table_names {
         let n = deps.add_node(Node {
             name: name.to_owned(),
             type_: "t".to_owned(),
         });
         names.insert(name, n);
     }
-    for (name, _) in views {
+    for name in view_names {
         let n = deps.add_node(Node {
             name: name.to_owned(),
             type_: "v".to_owned(),
@@ -326,10 +818,11 @@ pub fn to_dependency_graph(
             *view,
             Edge {
                 type_: "v->r".to_owned(),
+                name: usage.view_name.to_owned(),
             },
         );
     }
-    for (_, fk) in fkey_constraints {
+    for (_, fk) in fkeys {
         let src = names.get(&fk.table).unwrap();
         let dest = names.get(&fk.foreign_table).unwrap();
         deps.add_edge(
@@ -337,6 +830,7 @@ pub fn to_dependency_graph(
             *dest,
             Edge {
                 type_: "fk".to_owned(),
+                name: fk.name.to_owned(),
             },
         );
     }
@@ -347,113 +841,110 @@ pub struct ColInfo {
     name: String,
     data_type: PgType,
     nullable: bool,
+    default: Option<String>,        // as postgres prints it
+    sqlite_default: Option<String>, // `default`, if it could be translated
+    serial: bool,                   // owns a sequence, as serial and identity columns do
+    last_value: Option<i64>,        // of that sequence, if it's been used
+    rowid_alias: bool,              // declared `INTEGER PRIMARY KEY`
+    autoincrement: bool,
+    precision: Option<i32>, // of a NUMERIC column, if declared
+    scale: Option<i32>,
+    numeric: Option<Numeric>,   // how a NUMERIC column is stored
+    datetime: Option<Datetime>, // how a date or timestamp column is stored
 }
 
-impl fmt::Display for ColInfo {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let sqlite_type = sqlite_type_from_pg_type(&self.data_type)
-            .unwrap()
-            .to_string()
-            .to_ascii_uppercase();
-
-        write!(
-            f,
-            "{} {} -- {}",
-            self.name,
-            sqlite_type,
-            self.data_type.to_string().to_ascii_uppercase()
-        )?;
-        if self.nullable == false {
-            write!(f, " NOT NULL")?;
-        }
-        return Ok(());
+impl ColInfo {
+    /// A column that isn't read from postgres' catalogs, with no default.
+    fn new(name: &str, data_type: PgType, nullable: bool) -> ColInfo {
+        return ColInfo {
+            name: name.to_owned(),
+            data_type,
+            nullable,
+            default: None,
+            sqlite_default: None,
+            serial: false,
+            last_value: None,
+            rowid_alias: false,
+            autoincrement: false,
+            precision: None,
+            scale: None,
+            numeric: None,
+            datetime: None,
+        };
     }
 }
 
-impl fmt::Display for PkeyConstraint {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "CONSTRAINT {} PRIMARY KEY ({})",
-            self.name,
-            self.columns.join(", ")
-        )
+/// postgres integer types that can back a rowid alias.
+const ROWID_TYPES: &[PgType] = &[PgType::INT2, PgType::INT4, PgType::INT8];
+
+impl Table {
+    fn add_partition_column(&mut self, column: &str) {
+        if self.columns.contains_key(column) {
+            panic!(
+                "{} already has a column {}; pass a different --partition-column",
+                self.sqlite_name, column
+            );
+        }
+        let col = ColInfo::new(column, PgType::TEXT, false);
+        self.column_order.push(column.to_owned());
+        self.columns.insert(column.to_owned(), col);
+        self.partition_column = Some(column.to_owned());
     }
-}
 
-impl fmt::Display for UniqueConstraint {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "CONSTRAINT {} UNIQUE ({})",
-            self.name,
-            self.columns.join(", ")
-        )
+    /// `expr`, translated to SQLite, with references to columns whose values
+    /// are stored differently than in postgres rewritten to compare the same.
+    fn expr_to_sqlite(&self, expr: &str) -> Result<String, String> {
+        let expr = expr::rewrite_typed_literals(expr, datetime::literal_to_sqlite)?;
+        return expr::rewrite_columns(&expr::to_sqlite(&expr), |name| {
+            let col = match self.columns.get(name) {
+                Some(col) => col,
+                None => return Ok(None),
+            };
+            let column = quote::sqlite(name);
+            match (col.numeric, col.datetime) {
+                (Some(storage), _) => {
+                    return numeric::column_to_sqlite(&column, storage, col.scale).map(Some)
+                }
+                (None, Some(storage)) => {
+                    return Ok(Some(datetime::column_to_sqlite(&column, storage)))
+                }
+                (None, None) => return Ok(None),
+            }
+        });
     }
-}
-impl fmt::Display for FkeyConstraint {
-    /// https://www.sqlite.org/syntax/table-constraint.html
-    /// https://www.sqlite.org/syntax/foreign-key-clause.html
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {}({})",
-            self.name,
-            self.columns.join(", "),
-            self.foreign_table,
-            self.foreign_columns.join(", ")
-        )
+
+    /// Declare a single-column, sequence-backed integer primary key as
+    /// `INTEGER PRIMARY KEY`, making it an alias for SQLite's rowid, which
+    /// SQLite numbers itself. Other primary keys stay table constraints.
+    fn alias_rowid(&mut self, autoincrement: bool) {
+        let column = match self.pk_constraints.as_slice() {
+            [pk] if pk.columns.len() == 1 => &pk.columns[0],
+            _ => return,
+        };
+        let col = self.columns.get_mut(column).unwrap();
+        if !col.serial || !ROWID_TYPES.contains(&col.data_type) {
+            return;
+        }
+        col.rowid_alias = true;
+        col.autoincrement = autoincrement;
+        self.pk_constraints.clear();
     }
 }
 
-impl fmt::Display for Table {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let cols: Vec<String> = self
-            .column_order
+impl Index {
+    fn sqlite_keys(&self) -> Vec<String> {
+        return self
+            .keys
             .iter()
-            .map(|col_name| self.columns.get(col_name).unwrap())
-            .map(|col| format!("{}", col))
+            .zip(&self.descending)
+            .map(|(key, desc)| match desc {
+                true => format!("{} DESC", key),
+                false => key.to_owned(),
+            })
             .collect();
-        write!(
-            f,
-            "CREATE TABLE {} (\n  {}\n",
-            &self.name,
-            cols.join("\n  , "),
-        )?;
-        let fk_constraints = self
-            .fkey_constraints
-            .iter()
-            .map(|fk| format!("{}", fk))
-            .collect::<Vec<String>>();
-        let pk_constraints = self
-            .pk_constraints
-            .iter()
-            .map(|pk| format!("{}", pk))
-            .collect::<Vec<String>>();
-        let unique_constraints = self
-            .unique_constraints
-            .iter()
-            .map(|pk| format!("{}", pk))
-            .collect::<Vec<String>>();
-        let constraints = [pk_constraints, unique_constraints, fk_constraints].concat();
-        if self.fkey_constraints.len() > 0 {
-            write!(f, "  , {}\n", constraints.join("\n  , "))?;
-        }
-        write!(f, "); -- ~ {} rows\n", self.approx_n_rows)?;
-        Ok(())
     }
 }
 
-pub fn dump_table<'a, 'b>(
-    conn: &'a mut postgres::Client,
-    table: &'b str,
-) -> Result<postgres::RowIter<'a>, postgres::Error> {
-    let query = format!("select * from {}", table);
-    let statement: postgres::Statement = conn.prepare(&query)?;
-    let params: Vec<&str> = vec![];
-    return conn.query_raw(&statement, params.iter());
-}
-
 use postgres::Error as PgError;
 use rusqlite::{Error as SqliteErr, Transaction as SqliteTransaction};
 
@@ -461,6 +952,8 @@ use rusqlite::{Error as SqliteErr, Transaction as SqliteTransaction};
 pub enum SqlError {
     SqliteErr(SqliteErr),
     PgError(PgError),
+    ConnectError(ConnectError),
+    Io(std::io::Error),
 }
 
 impl From<SqliteErr> for SqlError {
@@ -473,32 +966,89 @@ impl From<PgError> for SqlError {
         return SqlError::PgError(e);
     }
 }
+impl From<ConnectError> for SqlError {
+    fn from(e: ConnectError) -> Self {
+        return SqlError::ConnectError(e);
+    }
+}
+impl From<std::io::Error> for SqlError {
+    fn from(e: std::io::Error) -> Self {
+        return SqlError::Io(e);
+    }
+}
 
 pub fn transfer_table_rows(
-    pg: &mut postgres::Client,
+    pg: &mut postgres::Transaction,
     lite: &mut SqliteTransaction,
     table: &Table,
+    extractor: Extractor,
 ) -> Result<(), SqlError> {
-    let mut rows = dump_table(pg, &table.name)?;
+    let mut rows = dump_table(pg, table, extractor)?;
     let col_params: Vec<String> = table.columns.iter().map(|_| "?".to_owned()).collect();
-    let cols: Vec<&ColInfo> = table
-        .column_order
-        .iter()
-        .map(|name| table.columns.get(name).unwrap())
-        .collect();
     let insert = format!(
         "INSERT INTO {} VALUES ({})",
-        &table.name,
+        &table.sqlite_ident,
         col_params.join(", ")
     );
     // pg and sqlite tables _MUST_ have the same name and column order
     let statement = &mut lite.prepare(&*insert)?; // causes stack overflow?
-    let countdown: u64 = (table.approx_n_rows).try_into().unwrap(); // safe since we don't expect negative numbers of rows
+    let countdown: u64 = (table.approx_n_rows).try_into().unwrap_or(0); // reltuples is -1 until a table is analyzed
     let pb = indicatif::ProgressBar::new(countdown);
 
-    while let Some(row) = rows.next()? {
-        statement.execute(translate_row(&row, &cols))?;
+    while rows.bind_next(statement)? {
+        statement.raw_execute()?;
         pb.inc(1);
     }
     Ok(())
 }
+
+#[test]
+fn test_ordering_relations_with_cycles() {
+    let mut g = Graph::new();
+    let mut node = |name: &str| {
+        return g.add_node(Node {
+            name: name.to_owned(),
+            type_: "t".to_owned(),
+        });
+    };
+    let (users, teams, tags, posts) = (node("users"), node("teams"), node("tags"), node("posts"));
+    let fk = |name: &str| Edge {
+        type_: "fk".to_owned(),
+        name: name.to_owned(),
+    };
+    g.add_edge(users, teams, fk("users_team_id_fkey"));
+    g.add_edge(teams, users, fk("teams_lead_id_fkey"));
+    g.add_edge(tags, tags, fk("tags_parent_id_fkey"));
+    g.add_edge(posts, users, fk("posts_author_id_fkey"));
+    g.add_edge(posts, tags, fk("posts_tag_id_fkey"));
+
+    let (order, cycles) = rel_order(&g);
+    let position = |name: &str| order.iter().position(|n| n == name).unwrap();
+    assert!(position("posts") > position("users"));
+    assert!(position("posts") > position("teams"));
+    assert!(position("posts") > position("tags"));
+    assert_eq!(
+        (position("teams") as i64 - position("users") as i64).abs(),
+        1
+    );
+
+    let mut cycles: Vec<(Vec<String>, Vec<String>)> =
+        cycles.into_iter().map(|c| (c.tables, c.fkeys)).collect();
+    cycles.sort();
+    assert_eq!(
+        cycles,
+        vec![
+            (
+                vec!["tags".to_owned()],
+                vec!["tags_parent_id_fkey (tags -> tags)".to_owned()]
+            ),
+            (
+                vec!["teams".to_owned(), "users".to_owned()],
+                vec![
+                    "teams_lead_id_fkey (teams -> users)".to_owned(),
+                    "users_team_id_fkey (users -> teams)".to_owned()
+                ]
+            ),
+        ]
+    );
+}
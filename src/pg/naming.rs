@@ -0,0 +1,128 @@
+use crate::quote;
+use std::path::Path;
+
+/// How relations from several postgres schemas share one SQLite namespace.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Naming {
+    /// a single schema: relations keep their own names
+    Bare,
+    /// relations are renamed `schema__relation`
+    Prefix,
+    /// the `main` schema goes into the destination database; every other
+    /// schema gets its own ATTACHed database, named after the schema.
+    Attach { main: String },
+}
+
+pub const NAMING_POLICIES: &[&str] = &["prefix", "attach"];
+
+impl Naming {
+    pub fn new(schemas: &[String], policy: &str) -> Naming {
+        if schemas.len() == 1 {
+            return Naming::Bare;
+        }
+        match policy {
+            "prefix" => Naming::Prefix,
+            "attach" => Naming::Attach {
+                main: schemas[0].to_owned(),
+            },
+            other => panic!("unknown schema naming policy {:?}", other),
+        }
+    }
+
+    /// The name to create, insert into, and select from in SQLite.
+    pub fn sqlite_name(&self, schema: &str, name: &str) -> String {
+        match self {
+            Naming::Bare => name.to_owned(),
+            Naming::Prefix => format!("{}__{}", schema, name),
+            Naming::Attach { main } if main == schema => name.to_owned(),
+            Naming::Attach { .. } => format!("{}.{}", schema, name),
+        }
+    }
+
+    /// `sqlite_name`, quoted for use in SQLite statements.
+    pub fn sqlite_ident(&self, schema: &str, name: &str) -> String {
+        match self {
+            Naming::Attach { main } if main != schema => {
+                quote::sqlite_qualified(Some(schema), name)
+            }
+            _ => quote::sqlite(&self.sqlite_name(schema, name)),
+        }
+    }
+
+    /// The name to use within the relation's own database, e.g. in a
+    /// `REFERENCES` clause, which SQLite doesn't allow to be qualified.
+    pub fn local_name(&self, schema: &str, name: &str) -> String {
+        match self {
+            Naming::Attach { .. } => name.to_owned(),
+            _ => self.sqlite_name(schema, name),
+        }
+    }
+
+    /// `name` within the database that holds `schema`'s relations, for
+    /// SQLite's own per-database tables like `sqlite_sequence`, quoted.
+    pub fn in_database(&self, schema: &str, name: &str) -> String {
+        match self {
+            Naming::Attach { main } if main != schema => {
+                quote::sqlite_qualified(Some(schema), name)
+            }
+            _ => quote::sqlite(name),
+        }
+    }
+
+    /// Whether a foreign key from `schema` to `foreign_schema` can be declared
+    /// in SQLite; foreign keys can't span attached databases.
+    pub fn can_reference(&self, schema: &str, foreign_schema: &str) -> bool {
+        match self {
+            Naming::Attach { .. } => schema == foreign_schema,
+            _ => true,
+        }
+    }
+
+    /// `(schema, path)` of the databases to attach alongside `dest`.
+    pub fn attachments(&self, schemas: &[String], dest: &str) -> Vec<(String, String)> {
+        let main = match self {
+            Naming::Attach { main } => main,
+            _ => return vec![],
+        };
+        let dest = Path::new(dest);
+        let stem = dest.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        let ext = dest.extension().and_then(|s| s.to_str()).unwrap_or("db");
+        return schemas
+            .iter()
+            .filter(|schema| *schema != main)
+            .map(|schema| {
+                let file = dest.with_file_name(format!("{}.{}.{}", stem, schema, ext));
+                (schema.to_owned(), file.to_string_lossy().into_owned())
+            })
+            .collect();
+    }
+}
+
+#[test]
+fn test_naming_policies() {
+    let schemas = vec!["public".to_owned(), "audit".to_owned()];
+    let prefix = Naming::new(&schemas, "prefix");
+    assert_eq!(prefix.sqlite_name("audit", "log"), "audit__log");
+    assert_eq!(prefix.local_name("audit", "log"), "audit__log");
+    let attach = Naming::new(&schemas, "attach");
+    assert_eq!(attach.sqlite_name("public", "users"), "users");
+    assert_eq!(attach.sqlite_name("audit", "log"), "audit.log");
+    assert_eq!(attach.local_name("audit", "log"), "log");
+    assert_eq!(
+        attach.in_database("audit", "sqlite_sequence"),
+        "audit.sqlite_sequence"
+    );
+    assert_eq!(
+        prefix.in_database("audit", "sqlite_sequence"),
+        "sqlite_sequence"
+    );
+    assert_eq!(attach.sqlite_ident("audit", "log"), "audit.log");
+    assert_eq!(attach.sqlite_ident("audit", "order"), "audit.\"order\"");
+    assert_eq!(prefix.sqlite_ident("Audit", "Log"), "Audit__Log");
+    assert!(!attach.can_reference("audit", "public"));
+    assert_eq!(
+        attach.attachments(&schemas, "./out/my.db"),
+        vec![("audit".to_owned(), "./out/my.audit.db".to_owned())]
+    );
+    assert_eq!(Naming::new(&schemas[..1], "attach"), Naming::Bare);
+}
@@ -0,0 +1,420 @@
+//! Store NUMERIC columns, which SQLite has no exact equivalent for, as text,
+//! doubles, scaled integers, or postgres' own binary format.
+
+use super::filter::Pattern;
+use super::{ColInfo, Table};
+use crate::quote;
+use postgres_types::{FromSql as FromPgSql, Type as PgType};
+use rusqlite::types::Type as SqliteType;
+use std::fmt::{self, Write};
+
+/// How to store a NUMERIC column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Numeric {
+    /// the exact decimal, as postgres prints it, e.g. `12.50`
+    Text,
+    /// the nearest double, which keeps about 15 significant digits
+    Real,
+    /// the value times 10^scale, e.g. `1250` for `12.50` in a NUMERIC(10, 2)
+    Integer,
+    /// postgres' binary format
+    Blob,
+}
+
+pub const NUMERIC_POLICIES: &[&str] = &["text", "real", "integer", "blob"];
+
+/// A double has 53 bits of mantissa: 15 decimal digits always survive a
+/// round trip through one.
+const REAL_DIGITS: i32 = 15;
+/// The most decimal digits that always fit in an `i64`.
+const INTEGER_DIGITS: i32 = 18;
+
+impl Numeric {
+    pub fn parse(policy: &str) -> Numeric {
+        match policy {
+            "text" => Numeric::Text,
+            "real" => Numeric::Real,
+            "integer" => Numeric::Integer,
+            "blob" => Numeric::Blob,
+            other => panic!("unknown numeric storage policy {:?}", other),
+        }
+    }
+
+    pub fn sqlite_type(&self) -> SqliteType {
+        match self {
+            Numeric::Text => SqliteType::Text,
+            Numeric::Real => SqliteType::Real,
+            Numeric::Integer => SqliteType::Integer,
+            Numeric::Blob => SqliteType::Blob,
+        }
+    }
+}
+
+/// A `--numeric-column TABLE.COLUMN=POLICY` override of `--numeric`, where
+/// TABLE is a pattern like those of `--include-table`.
+#[derive(Debug, Clone)]
+pub struct NumericColumn {
+    table: Pattern,
+    column: String,
+    policy: Numeric,
+}
+
+impl NumericColumn {
+    pub fn parse(arg: &str) -> Result<NumericColumn, String> {
+        let usage = || format!("expected TABLE.COLUMN=POLICY, got {:?}", arg);
+        let (target, policy) = match arg.rfind('=') {
+            Some(eq) => (&arg[..eq], &arg[eq + 1..]),
+            None => return Err(usage()),
+        };
+        let (table, column) = match target.rfind('.') {
+            Some(dot) if dot > 0 && dot < target.len() - 1 => (&target[..dot], &target[dot + 1..]),
+            _ => return Err(usage()),
+        };
+        if !NUMERIC_POLICIES.contains(&policy) {
+            return Err(format!(
+                "unknown numeric storage policy {:?}; expected one of {}",
+                policy,
+                NUMERIC_POLICIES.join(", ")
+            ));
+        }
+        return Ok(NumericColumn {
+            table: Pattern::parse(table)?,
+            column: column.to_owned(),
+            policy: Numeric::parse(policy),
+        });
+    }
+}
+
+/// How to store `table`'s NUMERIC column `col`: as the last of `overrides`
+/// that names it asks, or else as `default` asks. A column that can't be
+/// stored as a scaled INTEGER, having no declared scale or too many digits,
+/// is stored as TEXT instead.
+pub fn choose(
+    table: &Table,
+    col: &ColInfo,
+    default: Numeric,
+    overrides: &[NumericColumn],
+) -> Numeric {
+    let policy = overrides
+        .iter()
+        .rev()
+        .find(|o| o.column == col.name && o.table.matches(&table.schema, &table.name))
+        .map(|o| o.policy)
+        .unwrap_or(default);
+    let name = &table.sqlite_name;
+    match (policy, col.precision, col.scale) {
+        (Numeric::Real, Some(precision), _) if precision <= REAL_DIGITS => {}
+        (Numeric::Real, _, _) => println!(
+            "-- storing {}.{} as REAL, which keeps only about {} significant digits",
+            name, col.name, REAL_DIGITS
+        ),
+        (Numeric::Integer, Some(precision), Some(_)) if precision <= INTEGER_DIGITS => {}
+        (Numeric::Integer, Some(_), Some(_)) => {
+            println!(
+                "-- storing {}.{} as TEXT: more than {} digits don't fit in an INTEGER",
+                name, col.name, INTEGER_DIGITS
+            );
+            return Numeric::Text;
+        }
+        (Numeric::Integer, _, _) => {
+            println!(
+                "-- storing {}.{} as TEXT: it has no declared scale to multiply by",
+                name, col.name
+            );
+            return Numeric::Text;
+        }
+        _ => {}
+    }
+    return policy;
+}
+
+/// `decimal`'s sign, integer digits, and fractional digits, if it's a finite
+/// decimal like `12.50` or `-3`.
+fn split_decimal(decimal: &str) -> Result<(bool, &str, &str), String> {
+    let (negative, unsigned) = match decimal.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, decimal.strip_prefix('+').unwrap_or(decimal)),
+    };
+    let (int_part, frac_part) = match unsigned.find('.') {
+        Some(dot) => (&unsigned[..dot], &unsigned[dot + 1..]),
+        None => (unsigned, ""),
+    };
+    let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if int_part.len() + frac_part.len() == 0 || !all_digits(int_part) || !all_digits(frac_part) {
+        return Err(format!("{} isn't a finite decimal", decimal));
+    }
+    return Ok((negative, int_part, frac_part));
+}
+
+/// A decimal like `12.50` or `-3`, times 10^`scale`.
+pub fn scale_decimal(decimal: &str, scale: i32) -> Result<i64, String> {
+    let (negative, int_part, frac_part) = split_decimal(decimal)?;
+    let mut digits = format!("{}{}", int_part, frac_part);
+    let shift = scale as i64 - frac_part.len() as i64;
+    if shift >= 0 {
+        digits.push_str(&"0".repeat(shift as usize));
+    } else {
+        let keep = digits.len().saturating_sub((-shift) as usize);
+        if digits[keep..].chars().any(|c| c != '0') {
+            return Err(format!(
+                "{} has more than {} decimal places",
+                decimal, scale
+            ));
+        }
+        digits.truncate(keep);
+    }
+    let magnitude: i64 = match digits.trim_start_matches('0') {
+        "" => 0,
+        digits => digits
+            .parse()
+            .map_err(|_| format!("{} times 10^{} doesn't fit in an INTEGER", decimal, scale))?,
+    };
+    return Ok(if negative { -magnitude } else { magnitude });
+}
+
+/// `decimal` with at least `scale` decimal places, as postgres prints the
+/// values of a NUMERIC(precision, scale) column.
+fn pad_places(decimal: &str, scale: Option<i32>) -> String {
+    let places = match decimal.find('.') {
+        Some(dot) => decimal.len() - dot - 1,
+        None => 0,
+    };
+    let missing = match scale {
+        Some(scale) if scale > 0 && scale as usize > places => scale as usize - places,
+        _ => return decimal.to_owned(),
+    };
+    let point = if places == 0 && !decimal.ends_with('.') {
+        "."
+    } else {
+        ""
+    };
+    return format!("{}{}{}", decimal, point, "0".repeat(missing));
+}
+
+/// A NUMERIC column's default, as `expr::default_to_sqlite` translated it,
+/// stored the way the column's values are. Only constants can be converted.
+pub fn default_to_sqlite(
+    default: &str,
+    storage: Numeric,
+    scale: Option<i32>,
+) -> Result<String, String> {
+    let literal = default.trim_matches(|c| c == '\'' || c == '(' || c == ')');
+    if split_decimal(literal).is_err() {
+        return Err(format!("only constants can be stored as {:?}", storage));
+    }
+    match storage {
+        Numeric::Text => return Ok(quote::literal(&pad_places(literal, scale))),
+        Numeric::Real => return Ok(literal.to_owned()),
+        Numeric::Integer => return Ok(scale_decimal(literal, scale.unwrap_or(0))?.to_string()),
+        Numeric::Blob => return Err("postgres' binary format can't be written as SQL".to_owned()),
+    }
+}
+
+/// How a CHECK constraint or an index reads a NUMERIC `column` stored as
+/// `storage`, to compare it with numbers as postgres does.
+pub fn column_to_sqlite(
+    column: &str,
+    storage: Numeric,
+    scale: Option<i32>,
+) -> Result<String, String> {
+    let scale = scale.unwrap_or(0);
+    let power = 10i64
+        .checked_pow(scale.unsigned_abs())
+        .ok_or_else(|| format!("{} times 10^{} doesn't fit in an INTEGER", column, scale))?;
+    match storage {
+        Numeric::Text => return Ok(format!("CAST({} AS REAL)", column)),
+        Numeric::Real => return Ok(column.to_owned()),
+        Numeric::Integer if scale == 0 => return Ok(column.to_owned()),
+        Numeric::Integer if scale > 0 => return Ok(format!("({} / {}.0)", column, power)),
+        Numeric::Integer => return Ok(format!("({} * {})", column, power)),
+        Numeric::Blob => {
+            return Err(format!(
+                "{} is stored in postgres' binary format, which SQLite can't compare",
+                column
+            ))
+        }
+    }
+}
+
+const NUMERIC_POS: u16 = 0x0000;
+const NUMERIC_NEG: u16 = 0x4000;
+const NUMERIC_NAN: u16 = 0xC000;
+const NUMERIC_PINF: u16 = 0xD000;
+const NUMERIC_NINF: u16 = 0xF000;
+
+/// A NUMERIC in postgres' binary format: its sign, the base-10000 digits of
+/// its absolute value, the weight of the first of them, and how many decimal
+/// places to print.
+pub struct PgNumeric<'a> {
+    raw: &'a [u8],
+    weight: i16,
+    sign: u16,
+    dscale: u16,
+    digits: &'a [u8], // big-endian i16s
+}
+
+impl<'a> PgNumeric<'a> {
+    pub fn raw(&self) -> &'a [u8] {
+        return self.raw;
+    }
+
+    /// False for `NaN`, `Infinity`, and `-Infinity`.
+    pub fn is_finite(&self) -> bool {
+        return self.sign == NUMERIC_POS || self.sign == NUMERIC_NEG;
+    }
+
+    fn digit(&self, i: i32) -> i16 {
+        if i < 0 || (i as usize) * 2 >= self.digits.len() {
+            return 0;
+        }
+        let i = i as usize * 2;
+        return i16::from_be_bytes([self.digits[i], self.digits[i + 1]]);
+    }
+}
+
+impl<'a> FromPgSql<'a> for PgNumeric<'a> {
+    fn from_sql(
+        _: &PgType,
+        raw: &'a [u8],
+    ) -> Result<PgNumeric<'a>, Box<dyn std::error::Error + Sync + Send>> {
+        if raw.len() < 8 {
+            return Err("truncated numeric".into());
+        }
+        let word = |i: usize| [raw[i], raw[i + 1]];
+        let ndigits = i16::from_be_bytes(word(0));
+        let numeric = PgNumeric {
+            raw,
+            weight: i16::from_be_bytes(word(2)),
+            sign: u16::from_be_bytes(word(4)),
+            dscale: u16::from_be_bytes(word(6)),
+            digits: &raw[8..],
+        };
+        if ndigits < 0 || numeric.digits.len() != ndigits as usize * 2 {
+            return Err(format!("numeric with {} digits in {} bytes", ndigits, raw.len()).into());
+        }
+        match numeric.sign {
+            NUMERIC_POS | NUMERIC_NEG | NUMERIC_NAN | NUMERIC_PINF | NUMERIC_NINF => {}
+            sign => return Err(format!("numeric with unknown sign {:#x}", sign).into()),
+        }
+        return Ok(numeric);
+    }
+    fn accepts(pg_type: &PgType) -> bool {
+        return *pg_type == PgType::NUMERIC;
+    }
+}
+
+/// The decimal as postgres' `numeric_out` prints it.
+impl fmt::Display for PgNumeric<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.sign {
+            NUMERIC_NAN => return f.write_str("NaN"),
+            NUMERIC_PINF => return f.write_str("Infinity"),
+            NUMERIC_NINF => return f.write_str("-Infinity"),
+            NUMERIC_NEG => f.write_char('-')?,
+            _ => {}
+        }
+        if self.weight < 0 {
+            f.write_char('0')?;
+        }
+        for i in 0..=self.weight as i32 {
+            match i {
+                0 => write!(f, "{}", self.digit(i))?,
+                _ => write!(f, "{:04}", self.digit(i))?,
+            }
+        }
+        if self.dscale > 0 {
+            f.write_char('.')?;
+            let mut places = String::with_capacity(self.dscale as usize + 4);
+            let mut i = self.weight as i32 + 1;
+            while places.len() < self.dscale as usize {
+                write!(places, "{:04}", self.digit(i))?;
+                i += 1;
+            }
+            f.write_str(&places[..self.dscale as usize])?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+fn binary(ndigits: i16, weight: i16, sign: u16, dscale: u16, digits: &[i16]) -> Vec<u8> {
+    let mut raw = vec![];
+    for word in &[ndigits as u16, weight as u16, sign, dscale] {
+        raw.extend(&word.to_be_bytes());
+    }
+    for digit in digits {
+        raw.extend(&digit.to_be_bytes());
+    }
+    return raw;
+}
+
+#[test]
+fn test_printing_numerics() {
+    let cases: Vec<(Vec<u8>, &str)> = vec![
+        (binary(2, 0, NUMERIC_POS, 2, &[12, 5000]), "12.50"),
+        (binary(1, -1, NUMERIC_NEG, 5, &[12]), "-0.00120"),
+        (binary(2, 2, NUMERIC_POS, 0, &[1, 2]), "100020000"),
+        (binary(0, 0, NUMERIC_POS, 3, &[]), "0.000"),
+        (
+            binary(3, 1, NUMERIC_POS, 6, &[1, 2345, 6789]),
+            "12345.678900",
+        ),
+        (binary(0, 0, NUMERIC_NAN, 0, &[]), "NaN"),
+        (binary(0, 0, NUMERIC_NINF, 0, &[]), "-Infinity"),
+    ];
+    for (raw, expected) in cases {
+        let numeric = PgNumeric::from_sql(&PgType::NUMERIC, &raw).unwrap();
+        assert_eq!(numeric.to_string(), expected);
+    }
+    assert!(PgNumeric::from_sql(&PgType::NUMERIC, &binary(2, 0, 0, 0, &[1])).is_err());
+}
+
+#[test]
+fn test_scaling_decimals() {
+    assert_eq!(scale_decimal("12.50", 2), Ok(1250));
+    assert_eq!(scale_decimal("-0.001", 3), Ok(-1));
+    assert_eq!(scale_decimal("7", 2), Ok(700));
+    assert_eq!(scale_decimal("12300", -2), Ok(123));
+    assert_eq!(scale_decimal("0.000", 0), Ok(0));
+    assert!(scale_decimal("1.234", 2).is_err());
+    assert!(scale_decimal("NaN", 2).is_err());
+    assert!(scale_decimal("99999999999999999999", 0).is_err());
+    assert_eq!(
+        default_to_sqlite("'1.5'", Numeric::Integer, Some(2)),
+        Ok("150".to_owned())
+    );
+    assert_eq!(
+        default_to_sqlite("0.00", Numeric::Text, None),
+        Ok("'0.00'".to_owned())
+    );
+    assert_eq!(
+        default_to_sqlite("0", Numeric::Text, Some(2)),
+        Ok("'0.00'".to_owned())
+    );
+    assert!(default_to_sqlite("'NaN'", Numeric::Real, None).is_err());
+    assert_eq!(
+        column_to_sqlite("amount", Numeric::Integer, Some(2)),
+        Ok("(amount / 100.0)".to_owned())
+    );
+    assert_eq!(
+        column_to_sqlite("amount", Numeric::Integer, Some(-3)),
+        Ok("(amount * 1000)".to_owned())
+    );
+    assert_eq!(
+        column_to_sqlite("amount", Numeric::Text, Some(2)),
+        Ok("CAST(amount AS REAL)".to_owned())
+    );
+    assert!(column_to_sqlite("amount", Numeric::Blob, Some(2)).is_err());
+}
+
+#[test]
+fn test_parsing_numeric_columns() {
+    let col = NumericColumn::parse("ledger.*.amount=integer").unwrap();
+    assert!(col.table.matches("ledger", "entry"));
+    assert_eq!(
+        (col.column.as_str(), col.policy),
+        ("amount", Numeric::Integer)
+    );
+    assert!(NumericColumn::parse("amount=integer").is_err());
+    assert!(NumericColumn::parse("entry.amount=decimal").is_err());
+}
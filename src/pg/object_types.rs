@@ -1,14 +1,11 @@
-use bit_vec;
+use super::array;
+use super::datetime::{self, Datetime, Finite, PgTimetz};
+use super::numeric::{self, Numeric, PgNumeric};
 use chrono;
-use postgres::{Column as PgColumn, Error, Row as PgRow};
+use postgres::{binary_copy::BinaryCopyOutRow, Error, Row as PgRow};
 use postgres_types::{FromSql as FromPgSql, Type as PgType};
-use rusqlite::{
-    types::{Null as SqliteNull, Type as SqliteType},
-    ToSql as ToSqlite,
-};
-use serde_json;
-
-use super::ColInfo;
+use rusqlite::types::{Type as SqliteType, ValueRef};
+use std::fmt::{self, Write};
 
 pub fn pretty_relkind(relkind: &str) -> &str {
     match relkind {
@@ -19,7 +16,7 @@ pub fn pretty_relkind(relkind: &str) -> &str {
         "S" => return "sequence",
         "s" => return "special",
         "f" => return "foreign table",
-        "p" => return "partitioned view",
+        "p" => return "partitioned table",
         "I" => return "partitioned index",
         other => panic!("unexpected relkind {:?}", other),
     }
@@ -220,6 +217,12 @@ pub fn sqlite_type_from_pg_type(pg_type: &PgType) -> Result<SqliteType, String>
         | &PgType::NAME
         | &PgType::VARCHAR
         | &PgType::BPCHAR
+        | &PgType::NUMERIC // exactly, unless `--numeric` says otherwise
+        | &PgType::DATE // as ISO-8601, unless `--datetime` says otherwise
+        | &PgType::TIMESTAMP
+        | &PgType::TIMESTAMPTZ
+        | &PgType::TIME
+        | &PgType::TIMETZ
         | &PgType::UNKNOWN => Ok(SqliteType::Text),
 
         &PgType::JSON
@@ -227,18 +230,12 @@ pub fn sqlite_type_from_pg_type(pg_type: &PgType) -> Result<SqliteType, String>
         | &PgType::JSONB
         | &PgType::BIT
         | &PgType::VARBIT
-        | &PgType::INT2_VECTOR
         | &PgType::BYTEA => Ok(SqliteType::Blob),
 
-        &PgType::FLOAT4
-        | &PgType::FLOAT8
-        | &PgType::DATE
-        | &PgType::TIME
-        | &PgType::TIMESTAMP
-        | &PgType::TIMESTAMPTZ
-        | &PgType::TIMETZ
-        | &PgType::TIMETZ_ARRAY
-        | &PgType::NUMERIC => Ok(SqliteType::Real),
+        &PgType::FLOAT4 | &PgType::FLOAT8 => Ok(SqliteType::Real),
+
+        // as JSON, unless `--arrays table` moves the column to a table of its own
+        array if array::element_type(array).is_some() => Ok(SqliteType::Text),
         unknown => Err(format!(
             "unable to convert postgres type {:?} to a sqlite type",
             unknown
@@ -246,113 +243,377 @@ pub fn sqlite_type_from_pg_type(pg_type: &PgType) -> Result<SqliteType, String>
     }
 }
 
-fn translate_cell<'a, Intermediate>(
-    row: &'a PgRow,
-    index: usize,
-) -> Result<Box<dyn ToSqlite>, Error>
-where
-    Intermediate: ToSqlite,
-    Intermediate: 'static, // TODO: explain why these bounds are needed, and what they are
-    Intermediate: FromPgSql<'a>,
-{
-    match row.try_get::<usize, Intermediate>(index) {
-        Ok(t) => Ok(Box::new(t)),
-        Err(e) => Err(e),
+/// A row that cells can be decoded from: either a `Row` returned by a query or
+/// a `BinaryCopyOutRow` read from `COPY ... (FORMAT binary)`.
+pub trait CellSource {
+    fn try_get_cell<'a, T: FromPgSql<'a>>(&'a self, index: usize) -> Result<T, Error>;
+}
+
+impl CellSource for PgRow {
+    fn try_get_cell<'a, T: FromPgSql<'a>>(&'a self, index: usize) -> Result<T, Error> {
+        return self.try_get(index);
+    }
+}
+
+impl CellSource for BinaryCopyOutRow {
+    fn try_get_cell<'a, T: FromPgSql<'a>>(&'a self, index: usize) -> Result<T, Error> {
+        return self.try_get(index);
     }
 }
 
-fn try_translating_col<'a, 'b>(
-    row: &'a PgRow,
-    index: usize,
-    col: &'a PgColumn,
-    nullable: bool,
-) -> Result<Box<dyn ToSqlite>, Error> {
-    if nullable {
-        match col.type_() {
-            &PgType::CHAR => translate_cell::<'a, Option<i8>>(row, index),
-            &PgType::INT2 => translate_cell::<'a, Option<i16>>(row, index),
-            &PgType::INT4 => translate_cell::<'a, Option<i32>>(row, index),
-            &PgType::INT8 => translate_cell::<'a, Option<i64>>(row, index),
-            &PgType::FLOAT4 | &PgType::FLOAT8 => translate_cell::<'a, Option<f64>>(row, index),
-            &PgType::BOOL => translate_cell::<'a, Option<bool>>(row, index),
-            &PgType::BYTEA => translate_cell::<'a, Option<Vec<u8>>>(row, index),
+/// A cell's bytes in postgres' binary format, for types whose format is
+/// already what SQLite should store.
+struct Raw<'a>(&'a [u8]);
+
+impl<'a> FromPgSql<'a> for Raw<'a> {
+    fn from_sql(
+        _: &PgType,
+        raw: &'a [u8],
+    ) -> Result<Raw<'a>, Box<dyn std::error::Error + Sync + Send>> {
+        return Ok(Raw(raw));
+    }
+    fn accepts(_: &PgType) -> bool {
+        return true;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Decode {
+    Char,
+    Int2,
+    Int4,
+    Int8,
+    Float4,
+    Float8,
+    Bool,
+    Bytes,
+    Text,
+    Json,
+    Jsonb,
+    Date(Datetime),
+    Time,
+    Timetz,
+    Timestamp(Datetime),
+    Timestamptz(Datetime),
+    Uuid,
+    Bits,
+    Numeric(Numeric, i32), // and the scale of a scaled INTEGER
+}
+
+/// Moves one column's cells from postgres to SQLite. Binders are chosen once
+/// per table, and decode each cell straight into a `ValueRef` that borrows
+/// from the row, or from a buffer the binder reuses for dates and times.
+#[derive(Debug)]
+pub struct Binder {
+    decode: Decode,
+    scratch: String,
+    /// how many values SQLite has no equivalent for were stored as NULL
+    pub nulled: u64,
+}
+
+impl Binder {
+    pub fn new(pg_type: &PgType) -> Result<Binder, String> {
+        let decode = match pg_type {
+            &PgType::CHAR => Decode::Char,
+            &PgType::INT2 => Decode::Int2,
+            &PgType::INT4 => Decode::Int4,
+            &PgType::INT8 => Decode::Int8,
+            &PgType::FLOAT4 => Decode::Float4,
+            &PgType::FLOAT8 => Decode::Float8,
+            &PgType::BOOL => Decode::Bool,
+            &PgType::BYTEA => Decode::Bytes,
             &PgType::TEXT
             | &PgType::NAME
             | &PgType::VARCHAR
             | &PgType::BPCHAR
-            | &PgType::UNKNOWN => translate_cell::<'a, Option<String>>(row, index),
-            &PgType::JSON | &PgType::JSONB => {
-                translate_cell::<'a, Option<serde_json::Value>>(row, index)
+            | &PgType::UNKNOWN => Decode::Text,
+            &PgType::JSON => Decode::Json,
+            &PgType::JSONB => Decode::Jsonb,
+            &PgType::DATE => Decode::Date(Datetime::Text),
+            &PgType::TIME => Decode::Time,
+            &PgType::TIMETZ => Decode::Timetz,
+            &PgType::TIMESTAMP => Decode::Timestamp(Datetime::Text),
+            &PgType::TIMESTAMPTZ => Decode::Timestamptz(Datetime::Text),
+            &PgType::UUID => Decode::Uuid,
+            &PgType::BIT | &PgType::VARBIT => Decode::Bits,
+            &PgType::NUMERIC => Decode::Numeric(Numeric::Text, 0),
+            unknown => return Err(format!("unable to copy postgres type {:?}", unknown)),
+        };
+        return Ok(Binder {
+            decode,
+            scratch: String::new(),
+            nulled: 0,
+        });
+    }
+
+    /// A binder for a NUMERIC column stored as `storage`. `Numeric::Integer`
+    /// needs the column's `scale`.
+    pub fn numeric(storage: Numeric, scale: Option<i32>) -> Binder {
+        let scale = match (storage, scale) {
+            (Numeric::Integer, None) => panic!("a NUMERIC without a scale can't be scaled"),
+            (_, scale) => scale.unwrap_or(0),
+        };
+        return Binder {
+            decode: Decode::Numeric(storage, scale),
+            scratch: String::new(),
+            nulled: 0,
+        };
+    }
+
+    /// A binder for a DATE, TIMESTAMP, or TIMESTAMPTZ column stored as
+    /// `storage`. With `Datetime::TextWithOffset`, the column must be read
+    /// as `datetime::select_with_offset` formats it.
+    pub fn datetime(pg_type: &PgType, storage: Datetime) -> Binder {
+        let decode = match (pg_type, storage) {
+            (&PgType::DATE, _) => Decode::Date(storage),
+            (&PgType::TIMESTAMP, _) => Decode::Timestamp(storage),
+            (&PgType::TIMESTAMPTZ, _) => Decode::Timestamptz(storage),
+            (other, _) => panic!("{} isn't a date or timestamp", other),
+        };
+        return Binder {
+            decode,
+            scratch: String::new(),
+            nulled: 0,
+        };
+    }
+
+    fn format(&mut self, value: impl fmt::Display) -> ValueRef<'_> {
+        self.scratch.clear();
+        write!(self.scratch, "{}", value).unwrap(); // writing to a String can't fail
+        return ValueRef::Text(self.scratch.as_bytes());
+    }
+
+    /// Decode cell `index` of `row`. Values are stored the way rusqlite's
+    /// `ToSql` impls would store the corresponding rust types. A NUMERIC
+    /// `NaN`, or an infinite NUMERIC, date, or timestamp, is stored as NULL,
+    /// and counted in `nulled`.
+    pub fn cell<'a>(
+        &'a mut self,
+        row: &'a impl CellSource,
+        index: usize,
+    ) -> Result<ValueRef<'a>, Box<dyn std::error::Error + Sync + Send>> {
+        macro_rules! decode {
+            ($t:ty, $cell:ident => $value:expr) => {
+                match row.try_get_cell::<Option<$t>>(index)? {
+                    Some($cell) => $value,
+                    None => ValueRef::Null,
+                }
+            };
+        }
+        // `infinity` and `-infinity` have no SQLite equivalent
+        macro_rules! decode_finite {
+            ($t:ty, $cell:ident => $value:expr) => {
+                match row.try_get_cell::<Option<Finite<$t>>>(index)? {
+                    Some(Finite(Some($cell))) => $value,
+                    Some(Finite(None)) => {
+                        self.nulled += 1;
+                        ValueRef::Null
+                    }
+                    None => ValueRef::Null,
+                }
+            };
+        }
+        let cell = match self.decode {
+            Decode::Char => decode!(i8, n => ValueRef::Integer(n.into())),
+            Decode::Int2 => decode!(i16, n => ValueRef::Integer(n.into())),
+            Decode::Int4 => decode!(i32, n => ValueRef::Integer(n.into())),
+            Decode::Int8 => decode!(i64, n => ValueRef::Integer(n)),
+            Decode::Float4 => decode!(f32, x => ValueRef::Real(x.into())),
+            Decode::Float8 => decode!(f64, x => ValueRef::Real(x)),
+            Decode::Bool => decode!(bool, b => ValueRef::Integer(b.into())),
+            Decode::Bytes | Decode::Uuid => decode!(Raw, raw => ValueRef::Blob(raw.0)),
+            Decode::Text | Decode::Json => decode!(Raw, raw => ValueRef::Text(raw.0)),
+            // jsonb's binary format is a version byte followed by the JSON text
+            Decode::Jsonb => decode!(Raw, raw => ValueRef::Text(&raw.0[1..])),
+            // varbit's binary format is the number of bits followed by the bytes
+            Decode::Bits => decode!(Raw, raw => ValueRef::Blob(&raw.0[4..])),
+            Decode::Date(Datetime::Text) => decode_finite!(chrono::NaiveDate, d => {
+                self.format(d.format("%Y-%m-%d"))
+            }),
+            Decode::Date(storage) => decode_finite!(chrono::NaiveDate, d => {
+                datetime::instant(storage, d.and_time(chrono::NaiveTime::MIN).and_utc())
+            }),
+            Decode::Time => decode!(chrono::NaiveTime, t => self.format(t.format("%H:%M:%S%.f"))),
+            Decode::Timetz => decode!(PgTimetz, t => self.format(t)),
+            Decode::Timestamp(Datetime::Text) => decode_finite!(chrono::NaiveDateTime, ts => {
+                self.format(ts.format("%Y-%m-%dT%H:%M:%S%.f"))
+            }),
+            Decode::Timestamp(storage) => decode_finite!(chrono::NaiveDateTime, ts => {
+                datetime::instant(storage, ts.and_utc())
+            }),
+            // as `datetime::select_with_offset` formats it
+            Decode::Timestamptz(Datetime::TextWithOffset) => decode!(Raw, raw => match raw.0 {
+                b"infinity" | b"-infinity" => {
+                    self.nulled += 1;
+                    ValueRef::Null
+                }
+                text => ValueRef::Text(text),
+            }),
+            Decode::Timestamptz(Datetime::Text) => {
+                decode_finite!(chrono::DateTime<chrono::Utc>, ts => {
+                    self.format(ts.format("%Y-%m-%dT%H:%M:%S%.f+00:00"))
+                })
             }
-            &PgType::DATE => translate_cell::<'a, Option<chrono::NaiveDate>>(row, index),
-            &PgType::TIME => translate_cell::<'a, Option<chrono::NaiveTime>>(row, index),
-            // &PgType::TIMETZ ?
-            &PgType::TIMESTAMP => translate_cell::<'a, Option<chrono::NaiveDateTime>>(row, index),
-            &PgType::TIMESTAMPTZ => {
-                translate_cell::<'a, Option<chrono::DateTime<chrono::Utc>>>(row, index)
+            Decode::Timestamptz(storage) => {
+                decode_finite!(chrono::DateTime<chrono::Utc>, ts => datetime::instant(storage, ts))
             }
-            &PgType::UUID => translate_cell::<'a, Option<uuid::Uuid>>(row, index),
-            &PgType::BIT | &PgType::VARBIT => {
-                match row.try_get::<usize, Option<bit_vec::BitVec>>(index) {
-                    Ok(result) => match result {
-                        Some(bits) => Ok(Box::new(bits.to_bytes())),
-                        None => Ok(Box::new(SqliteNull)),
+            Decode::Numeric(storage, scale) => {
+                match row.try_get_cell::<Option<PgNumeric>>(index)? {
+                    None => ValueRef::Null,
+                    Some(n) if !n.is_finite() => {
+                        self.nulled += 1;
+                        ValueRef::Null
+                    }
+                    Some(n) => match storage {
+                        Numeric::Text => self.format(n),
+                        Numeric::Real => ValueRef::Real(n.to_string().parse::<f64>()?),
+                        Numeric::Integer => {
+                            ValueRef::Integer(numeric::scale_decimal(&n.to_string(), scale)?)
+                        }
+                        Numeric::Blob => ValueRef::Blob(n.raw()),
                     },
-                    Err(e) => Err(e),
                 }
             }
-            _ => unimplemented!(),
-        }
-    } else {
-        match col.type_() {
-            &PgType::CHAR => translate_cell::<'a, i8>(row, index),
-            &PgType::INT2 => translate_cell::<'a, i16>(row, index),
-            &PgType::INT4 => translate_cell::<'a, i32>(row, index),
-            &PgType::INT8 => translate_cell::<'a, i64>(row, index),
-            &PgType::FLOAT4 | &PgType::FLOAT8 => translate_cell::<'a, f64>(row, index),
-            &PgType::BOOL => translate_cell::<'a, bool>(row, index),
-            &PgType::BYTEA => translate_cell::<'a, Vec<u8>>(row, index),
-            &PgType::TEXT
-            | &PgType::NAME
-            | &PgType::VARCHAR
-            | &PgType::BPCHAR
-            | &PgType::UNKNOWN => translate_cell::<'a, String>(row, index),
-            &PgType::JSON | &PgType::JSONB => translate_cell::<'a, serde_json::Value>(row, index),
-            &PgType::DATE => translate_cell::<'a, chrono::NaiveDate>(row, index),
-            &PgType::TIME => translate_cell::<'a, chrono::NaiveTime>(row, index),
-            // &PgType::TIMETZ ?
-            &PgType::TIMESTAMP => translate_cell::<'a, chrono::NaiveDateTime>(row, index),
-            &PgType::TIMESTAMPTZ => translate_cell::<'a, chrono::DateTime<chrono::Utc>>(row, index),
-            &PgType::UUID => translate_cell::<'a, uuid::Uuid>(row, index),
-            &PgType::BIT | &PgType::VARBIT => match row.try_get::<usize, bit_vec::BitVec>(index) {
-                Ok(bits) => Ok(Box::new(bits.to_bytes())),
-                Err(e) => Err(e),
-            },
-            _ => unimplemented!(),
-        }
+        };
+        return Ok(cell);
     }
 }
 
-fn translate_col<'a, 'b>(
-    row: &'a PgRow,
-    index: usize,
-    col: &'a PgColumn,
-    info: &'b ColInfo,
-) -> Box<dyn ToSqlite> {
-    match try_translating_col(row, index, col, info.nullable) {
-        Ok(result) => result,
-        Err(e) => {
-            panic!("error {} in {:?} ({})", e, row, info)
-        }
+#[cfg(test)]
+struct Cells(Vec<(PgType, Option<Vec<u8>>)>);
+
+#[cfg(test)]
+impl CellSource for Cells {
+    fn try_get_cell<'a, T: FromPgSql<'a>>(&'a self, index: usize) -> Result<T, Error> {
+        let (pg_type, raw) = &self.0[index];
+        return Ok(T::from_sql_nullable(pg_type, raw.as_deref()).unwrap());
     }
 }
 
-pub fn translate_row(row: &PgRow, cols: &Vec<&ColInfo>) -> Vec<Box<dyn ToSqlite>> {
-    return row
-        .columns()
+#[test]
+fn test_binders_store_what_to_sql_would() {
+    use chrono::TimeZone;
+    use rusqlite::{
+        types::{ToSqlOutput, Value},
+        ToSql,
+    };
+    let date = chrono::NaiveDate::from_ymd_opt(2020, 2, 29).unwrap();
+    let time = chrono::NaiveTime::from_hms_micro_opt(12, 34, 56, 500_000).unwrap();
+    let epoch = chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+    let micros = (date.and_time(time) - epoch.and_time(chrono::NaiveTime::MIN))
+        .num_microseconds()
+        .unwrap();
+    let row = Cells(vec![
+        (PgType::DATE, Some(7364i32.to_be_bytes().to_vec())),
+        (PgType::TIME, Some(45_296_500_000i64.to_be_bytes().to_vec())),
+        (PgType::TIMESTAMP, Some(micros.to_be_bytes().to_vec())),
+        (PgType::TIMESTAMPTZ, Some(micros.to_be_bytes().to_vec())),
+        (PgType::FLOAT4, Some(1.5f32.to_be_bytes().to_vec())),
+        (PgType::JSONB, Some(b"\x01{\"a\": 1}".to_vec())),
+        (PgType::VARBIT, Some(vec![0, 0, 0, 10, 0xd9, 0xc0])),
+        (PgType::TEXT, None),
+    ]);
+    let expected: Vec<Box<dyn ToSql>> = vec![
+        Box::new(date),
+        Box::new(time),
+        Box::new(date.and_time(time)),
+        Box::new(chrono::Utc.from_utc_datetime(&date.and_time(time))),
+        Box::new(1.5f64),
+        Box::new("{\"a\": 1}"),
+        Box::new(vec![0xd9u8, 0xc0]),
+        Box::new(rusqlite::types::Null),
+    ];
+    for (index, (pg_type, _)) in row.0.iter().enumerate() {
+        let mut binder = Binder::new(pg_type).unwrap();
+        let cell: Value = binder.cell(&row, index).unwrap().into();
+        let expected: Value = match expected[index].to_sql().unwrap() {
+            ToSqlOutput::Borrowed(value) => value.into(),
+            ToSqlOutput::Owned(value) => value,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(cell, expected, "{}", pg_type);
+    }
+}
+
+#[test]
+fn test_binding_numerics() {
+    use rusqlite::types::Value;
+    // 12.34: digits 12 and 3400, the first of weight 0, printed with 2 places
+    let raw: Vec<u8> = [2i16, 0, 0, 2, 12, 3400]
         .iter()
-        .zip(cols)
-        .enumerate()
-        .map(|(idx, (col, colinfo))| translate_col(row, idx, col, colinfo))
+        .flat_map(|word| word.to_be_bytes().to_vec())
         .collect();
+    let row = Cells(vec![(PgType::NUMERIC, Some(raw.clone()))]);
+    let cases = vec![
+        (Numeric::Text, Value::Text("12.34".to_owned())),
+        (Numeric::Real, Value::Real(12.34)),
+        (Numeric::Integer, Value::Integer(1234)),
+        (Numeric::Blob, Value::Blob(raw)),
+    ];
+    for (storage, expected) in cases {
+        let mut binder = Binder::numeric(storage, Some(2));
+        let cell: Value = binder.cell(&row, 0).unwrap().into();
+        assert_eq!(cell, expected, "{:?}", storage);
+    }
+    let mut binder = Binder::numeric(Numeric::Integer, Some(1));
+    assert!(binder.cell(&row, 0).is_err()); // 12.34 has a second decimal place
+
+    // NaN, the same in every storage
+    let nan = Cells(vec![(
+        PgType::NUMERIC,
+        Some(vec![0, 0, 0, 0, 0xc0, 0, 0, 0]),
+    )]);
+    for storage in &[
+        Numeric::Text,
+        Numeric::Real,
+        Numeric::Integer,
+        Numeric::Blob,
+    ] {
+        let mut binder = Binder::numeric(*storage, Some(2));
+        let cell: Value = binder.cell(&nan, 0).unwrap().into();
+        assert_eq!((cell, binder.nulled), (Value::Null, 1), "{:?}", storage);
+    }
+}
+
+#[test]
+fn test_binding_datetimes() {
+    use rusqlite::types::Value;
+    // 2020-02-29 12:00:00, in days and microseconds since 2000-01-01
+    let row = Cells(vec![
+        (PgType::DATE, Some(7364i32.to_be_bytes().to_vec())),
+        (
+            PgType::TIMESTAMP,
+            Some(636_292_800_000_000i64.to_be_bytes().to_vec()),
+        ),
+        (
+            PgType::TIMESTAMPTZ,
+            Some(636_292_800_000_000i64.to_be_bytes().to_vec()),
+        ),
+    ]);
+    let cases = vec![
+        (Datetime::Unix, [1582934400, 1582977600, 1582977600]),
+        (
+            Datetime::UnixMillis,
+            [1582934400000, 1582977600000, 1582977600000],
+        ),
+    ];
+    for (storage, expected) in cases {
+        for (index, (pg_type, _)) in row.0.iter().enumerate() {
+            let mut binder = Binder::datetime(pg_type, storage);
+            let cell: Value = binder.cell(&row, index).unwrap().into();
+            assert_eq!(
+                cell,
+                Value::Integer(expected[index]),
+                "{:?} {}",
+                storage,
+                pg_type
+            );
+        }
+    }
+    let mut binder = Binder::datetime(&PgType::DATE, Datetime::Julian);
+    let cell: Value = binder.cell(&row, 0).unwrap().into();
+    assert_eq!(cell, Value::Real(2458908.5));
+    let mut binder = Binder::datetime(&PgType::TIMESTAMPTZ, Datetime::Text);
+    let cell: Value = binder.cell(&row, 2).unwrap().into();
+    assert_eq!(cell, Value::Text("2020-02-29T12:00:00+00:00".to_owned()));
 }
@@ -0,0 +1,140 @@
+//! Read tables over several connections that share one exported snapshot,
+//! while a single writer inserts them into SQLite in dependency order.
+
+use super::query::{self, ConnectError};
+use super::{dump_table, Extractor, SqlError, Table};
+use rusqlite::{types::Value, Transaction as SqliteTransaction};
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::panic;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::Mutex;
+use std::thread;
+
+/// rows are sent to the writer in batches of this size.
+const ROWS_PER_CHUNK: usize = 1024;
+/// how many batches a worker may read ahead of the writer, per table.
+const CHUNKS_IN_FLIGHT: usize = 4;
+
+enum Chunk {
+    Rows(Vec<Vec<Value>>),
+    /// every row of the table has been sent
+    Done,
+}
+
+type Job<'t> = (&'t Table, SyncSender<Chunk>);
+
+/// Claim tables in order until none are left, sending their rows to the writer.
+fn read_tables<F>(
+    connect: &F,
+    snapshot: &str,
+    extractor: Extractor,
+    queue: &Mutex<VecDeque<Job>>,
+) -> Result<(), SqlError>
+where
+    F: Fn() -> Result<postgres::Client, ConnectError>,
+{
+    let mut client = connect()?;
+    let mut txn = query::import_snapshot(&mut client, snapshot)?;
+    loop {
+        let next = queue.lock().unwrap().pop_front();
+        let (table, rows_out) = match next {
+            Some(job) => job,
+            None => break,
+        };
+        let mut rows = dump_table(&mut txn, table, extractor)?;
+        let mut chunk = Vec::with_capacity(ROWS_PER_CHUNK);
+        while let Some(values) = rows.next_values()? {
+            chunk.push(values);
+            if chunk.len() == ROWS_PER_CHUNK {
+                let full = std::mem::replace(&mut chunk, Vec::with_capacity(ROWS_PER_CHUNK));
+                if rows_out.send(Chunk::Rows(full)).is_err() {
+                    return Ok(()); // the writer stopped; it'll report why
+                }
+            }
+        }
+        if rows_out.send(Chunk::Rows(chunk)).is_err() || rows_out.send(Chunk::Done).is_err() {
+            return Ok(());
+        }
+    }
+    txn.commit()?;
+    return Ok(());
+}
+
+/// Insert each table's rows as they arrive. Returns the name of the first
+/// table whose reader stopped before sending all of its rows, if any.
+fn write_tables(
+    lite: &mut SqliteTransaction,
+    tables: &[&Table],
+    receivers: Vec<Receiver<Chunk>>,
+) -> Result<Option<String>, SqlError> {
+    for (table, rows_in) in tables.iter().zip(receivers) {
+        println!("transferring {}", &table.sqlite_name);
+        let col_params: Vec<&str> = table.columns.iter().map(|_| "?").collect();
+        let insert = format!(
+            "INSERT INTO {} VALUES ({})",
+            &table.sqlite_ident,
+            col_params.join(", ")
+        );
+        let mut statement = lite.prepare(&insert)?;
+        let countdown: u64 = (table.approx_n_rows).try_into().unwrap_or(0);
+        let pb = indicatif::ProgressBar::new(countdown);
+        loop {
+            match rows_in.recv() {
+                Ok(Chunk::Rows(rows)) => {
+                    for row in &rows {
+                        statement.execute(row)?;
+                    }
+                    pb.inc(rows.len() as u64);
+                }
+                Ok(Chunk::Done) => break,
+                Err(_) => return Ok(Some(table.sqlite_name.to_owned())),
+            }
+        }
+    }
+    return Ok(None);
+}
+
+/// Copy `tables`, in order, using `jobs` connections made by `connect`, each
+/// reading from the exported `snapshot`. The exporting transaction must stay
+/// open until this returns.
+pub fn transfer_tables<F>(
+    jobs: usize,
+    connect: F,
+    snapshot: &str,
+    extractor: Extractor,
+    lite: &mut SqliteTransaction,
+    tables: &[&Table],
+) -> Result<(), SqlError>
+where
+    F: Fn() -> Result<postgres::Client, ConnectError> + Sync,
+{
+    let (senders, receivers): (Vec<_>, Vec<_>) = tables
+        .iter()
+        .map(|_| sync_channel::<Chunk>(CHUNKS_IN_FLIGHT))
+        .unzip();
+    // tables are claimed in the order they're written, so the writer is
+    // never stuck waiting on a table nobody is reading.
+    let queue: Mutex<VecDeque<Job>> = Mutex::new(tables.iter().copied().zip(senders).collect());
+    return thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| scope.spawn(|| read_tables(&connect, snapshot, extractor, &queue)))
+            .collect();
+        let written = write_tables(lite, tables, receivers);
+        // stop workers from claiming more tables if the writer gave up early
+        queue.lock().unwrap().clear();
+        let mut failure = None;
+        for worker in workers {
+            match worker.join() {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => failure = failure.or(Some(e)),
+                Err(cause) => panic::resume_unwind(cause),
+            }
+        }
+        match (written, failure) {
+            (Err(e), _) | (Ok(_), Some(e)) => return Err(e),
+            (Ok(Some(table)), None) => panic!("stopped reading {} before it was copied", table),
+            (Ok(None), None) => return Ok(()),
+        }
+    });
+}
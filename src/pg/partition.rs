@@ -0,0 +1,124 @@
+//! Copy declaratively partitioned tables either as one SQLite table per
+//! partitioned table, or as one per leaf partition.
+
+use super::{FkeyConstraint, Naming, Rel};
+use std::collections::{HashMap, HashSet};
+
+/// How to copy a partitioned table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Partitions {
+    /// one table under the partitioned table's name, holding every partition's rows
+    Merge,
+    /// each leaf partition as its own table
+    Separate,
+}
+
+pub const PARTITION_POLICIES: &[&str] = &["merge", "separate"];
+
+impl Partitions {
+    pub fn parse(policy: &str) -> Partitions {
+        match policy {
+            "merge" => Partitions::Merge,
+            "separate" => Partitions::Separate,
+            other => panic!("unknown partition policy {:?}", other),
+        }
+    }
+}
+
+/// Drop the relations `policy` doesn't copy: partitions, when merging, or
+/// partitioned tables, which hold no rows of their own, when not. Foreign
+/// keys to or from dropped relations are dropped, too, as are the copies
+/// postgres makes of a foreign key for each partition of the table it
+/// references; none of those make sense for a single partition.
+pub fn select_partitions(
+    rels: Vec<Rel>,
+    fkeys: Vec<FkeyConstraint>,
+    policy: Partitions,
+    naming: &Naming,
+) -> (Vec<Rel>, Vec<FkeyConstraint>) {
+    let mut approx_n_rows: HashMap<u32, i64> = HashMap::new();
+    for rel in &rels {
+        if let (Some(root), "table") = (rel.partition_root, rel.relkind.as_str()) {
+            *approx_n_rows.entry(root).or_insert(0) += rel.approx_n_rows.max(0);
+        }
+    }
+    let (kept, dropped): (Vec<Rel>, Vec<Rel>) = rels.into_iter().partition(|rel| match policy {
+        Partitions::Merge => rel.partition_root.is_none(),
+        Partitions::Separate => rel.relkind != "partitioned table",
+    });
+    let dropped: HashSet<String> = dropped
+        .iter()
+        .map(|rel| naming.sqlite_name(&rel.schema, &rel.name))
+        .collect();
+    let partitions: HashSet<String> = kept
+        .iter()
+        .filter(|rel| rel.partition_root.is_some())
+        .map(|rel| naming.sqlite_name(&rel.schema, &rel.name))
+        .collect();
+    let kept = kept
+        .into_iter()
+        .map(|mut rel| {
+            if let Some(n) = approx_n_rows.get(&rel.oid) {
+                rel.approx_n_rows = *n; // a partitioned table's own estimate is always empty
+            }
+            rel
+        })
+        .collect();
+    let fkeys = fkeys
+        .into_iter()
+        .filter(|fk| {
+            if dropped.contains(&fk.table) {
+                return false;
+            }
+            if fk.inherited && partitions.contains(&fk.foreign_table) {
+                return false; // postgres' copy for one partition of the referenced table
+            }
+            if !dropped.contains(&fk.foreign_table) {
+                return true;
+            }
+            if policy == Partitions::Separate && !fk.inherited {
+                println!(
+                    "-- dropping foreign key {} from {} to partitioned table {}: \
+                    its partitions are copied separately",
+                    fk.name, fk.table, fk.foreign_table
+                );
+            }
+            false
+        })
+        .collect();
+    return (kept, fkeys);
+}
+
+#[test]
+fn test_merging_and_separating_partitions() {
+    let rel = |oid: u32, name: &str, relkind: &str, root: Option<u32>, n: i64| Rel {
+        oid,
+        schema: "public".to_owned(),
+        name: name.to_owned(),
+        relkind: relkind.to_owned(),
+        approx_n_rows: n,
+        populated: true,
+        partition_root: root,
+        inherits: false,
+    };
+    let rels = || {
+        vec![
+            rel(1, "events", "partitioned table", None, -1),
+            rel(2, "events_2020", "partitioned table", Some(1), -1),
+            rel(3, "events_2020_q1", "table", Some(1), 10),
+            rel(4, "events_2021", "table", Some(1), 5),
+            rel(5, "users", "table", None, 3),
+        ]
+    };
+    let names = |rels: &[Rel]| -> Vec<String> { rels.iter().map(|r| r.name.to_owned()).collect() };
+
+    let (merged, _) = select_partitions(rels(), vec![], Partitions::Merge, &Naming::Bare);
+    assert_eq!(names(&merged), vec!["events", "users"]);
+    assert_eq!(merged[0].approx_n_rows, 15);
+
+    let (separate, _) = select_partitions(rels(), vec![], Partitions::Separate, &Naming::Bare);
+    assert_eq!(
+        names(&separate),
+        vec!["events_2020_q1", "events_2021", "users"]
+    );
+}
@@ -1,23 +1,49 @@
+-- `conkey` and `confkey` pair the referencing and referenced columns by
+-- position, so each is unnested WITH ORDINALITY to keep that pairing.
 SELECT
-  tc.constraint_name
-  , tc.constraint_type
-  , tc.table_name
-  , array_agg(kcu.column_name::TEXT) AS columns
-  , ccu.table_name AS foreign_table_name
-  , array_agg(ccu.column_name::TEXT) AS foreign_columns
-  , tc.is_deferrable
-  , tc.initially_deferred
-FROM information_schema.table_constraints AS tc
-  JOIN information_schema.key_column_usage AS kcu
-    ON tc.constraint_name = kcu.constraint_name
-  JOIN information_schema.constraint_column_usage AS ccu
-    ON tc.constraint_name = ccu.constraint_name
-WHERE tc.constraint_schema = $1 AND tc.constraint_type = 'FOREIGN KEY'
-GROUP BY
-  tc.constraint_name
-  , tc.constraint_type
-  , tc.table_name
-  , tc.is_deferrable
-  , tc.initially_deferred
-  , ccu.table_name
-        
\ No newline at end of file
+  con.oid
+  , con.conname AS constraint_name
+  , ns.nspname AS table_schema
+  , rel.relname AS table_name
+  , ARRAY(
+      SELECT att.attname::TEXT
+      FROM unnest(con.conkey) WITH ORDINALITY AS k(attnum, n)
+        JOIN pg_catalog.pg_attribute AS att
+          ON att.attrelid = con.conrelid AND att.attnum = k.attnum
+      ORDER BY k.n
+    ) AS columns
+  , fns.nspname AS foreign_table_schema
+  , frel.relname AS foreign_table_name
+  , ARRAY(
+      SELECT att.attname::TEXT
+      FROM unnest(con.confkey) WITH ORDINALITY AS k(attnum, n)
+        JOIN pg_catalog.pg_attribute AS att
+          ON att.attrelid = con.confrelid AND att.attnum = k.attnum
+      ORDER BY k.n
+    ) AS foreign_columns
+  -- NULL for NO ACTION and MATCH SIMPLE, the defaults in both databases
+  , CASE con.confdeltype
+      WHEN 'r' THEN 'RESTRICT'
+      WHEN 'c' THEN 'CASCADE'
+      WHEN 'n' THEN 'SET NULL'
+      WHEN 'd' THEN 'SET DEFAULT'
+    END AS on_delete
+  , CASE con.confupdtype
+      WHEN 'r' THEN 'RESTRICT'
+      WHEN 'c' THEN 'CASCADE'
+      WHEN 'n' THEN 'SET NULL'
+      WHEN 'd' THEN 'SET DEFAULT'
+    END AS on_update
+  , CASE con.confmatchtype WHEN 'f' THEN 'FULL' WHEN 'p' THEN 'PARTIAL' END AS match_type
+  , con.condeferrable AS is_deferrable
+  , con.condeferred AS initially_deferred
+  -- postgres' copy of a foreign key on a partitioned table, either on one of
+  -- its partitions or referencing one of the referenced table's partitions
+  , con.conparentid <> 0 AS is_inherited
+FROM pg_catalog.pg_constraint AS con
+  JOIN pg_catalog.pg_class AS rel ON rel.oid = con.conrelid
+  JOIN pg_catalog.pg_namespace AS ns ON ns.oid = rel.relnamespace
+  JOIN pg_catalog.pg_class AS frel ON frel.oid = con.confrelid
+  JOIN pg_catalog.pg_namespace AS fns ON fns.oid = frel.relnamespace
+WHERE con.contype = 'f' AND ns.nspname = ANY($1)
+ORDER BY ns.nspname, rel.relname, con.conname;
@@ -1,14 +1,19 @@
 SELECT
-  tc.constraint_name
-  , tc.table_name
-  , array_agg(kcu.column_name::TEXT) AS columns
-  , tc.is_deferrable
-  , tc.initially_deferred
-FROM information_schema.table_constraints AS tc
-  JOIN information_schema.key_column_usage kcu ON tc.constraint_name = kcu.constraint_name
-WHERE tc.constraint_schema = $1 AND tc.constraint_type = 'UNIQUE'
-GROUP BY tc.constraint_name,
-  tc.constraint_type,
-  tc.table_name,
-  tc.is_deferrable,
-  tc.initially_deferred;
+  con.oid
+  , con.conname AS constraint_name
+  , ns.nspname AS table_schema
+  , rel.relname AS table_name
+  , ARRAY(
+      SELECT att.attname::TEXT
+      FROM unnest(con.conkey) WITH ORDINALITY AS k(attnum, n)
+        JOIN pg_catalog.pg_attribute AS att
+          ON att.attrelid = con.conrelid AND att.attnum = k.attnum
+      ORDER BY k.n
+    ) AS columns
+  , con.condeferrable AS is_deferrable
+  , con.condeferred AS initially_deferred
+FROM pg_catalog.pg_constraint AS con
+  JOIN pg_catalog.pg_class AS rel ON rel.oid = con.conrelid
+  JOIN pg_catalog.pg_namespace AS ns ON ns.oid = rel.relnamespace
+WHERE con.contype = 'u' AND ns.nspname = ANY($1)
+ORDER BY ns.nspname, rel.relname, con.conname;
@@ -0,0 +1,11 @@
+SELECT
+  con.conname AS constraint_name
+  , ns.nspname AS table_schema
+  , rel.relname AS table_name
+  , pg_get_constraintdef(con.oid) AS defn
+  , pg_get_expr(con.conbin, con.conrelid) AS expr
+FROM pg_catalog.pg_constraint AS con
+  JOIN pg_catalog.pg_class AS rel ON rel.oid = con.conrelid
+  JOIN pg_catalog.pg_namespace AS ns ON ns.oid = rel.relnamespace
+WHERE con.contype = 'c' AND ns.nspname = ANY($1)
+ORDER BY ns.nspname, rel.relname, con.conname;
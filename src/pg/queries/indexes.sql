@@ -0,0 +1,37 @@
+-- indexes that aren't there to back a primary key, unique, or exclusion constraint
+SELECT
+  ic.relname AS index_name
+  , ns.nspname AS table_schema
+  , t.relname AS table_name
+  , am.amname AS method
+  , i.indisunique AS is_unique
+  , pg_get_indexdef(i.indexrelid) AS defn
+  , ARRAY(
+      SELECT pg_get_indexdef(i.indexrelid, k, true)
+      FROM generate_series(1, i.indnkeyatts) AS k
+      ORDER BY k
+    ) AS keys
+  , ARRAY(
+      SELECT att.attname::TEXT -- NULL for an expression
+      FROM generate_series(1, i.indnkeyatts) AS k
+        LEFT JOIN pg_catalog.pg_attribute AS att
+          ON att.attrelid = i.indrelid AND att.attnum = i.indkey[k - 1]
+      ORDER BY k
+    ) AS key_columns
+  , ARRAY(
+      SELECT (i.indoption[k - 1]::INT & 1) = 1 -- INDOPTION_DESC
+      FROM generate_series(1, i.indnkeyatts) AS k
+      ORDER BY k
+    ) AS descending
+  , pg_get_expr(i.indpred, i.indrelid) AS predicate
+FROM pg_catalog.pg_index AS i
+  JOIN pg_catalog.pg_class AS ic ON ic.oid = i.indexrelid
+  JOIN pg_catalog.pg_class AS t ON t.oid = i.indrelid
+  JOIN pg_catalog.pg_namespace AS ns ON ns.oid = t.relnamespace
+  JOIN pg_catalog.pg_am AS am ON am.oid = ic.relam
+WHERE ns.nspname = ANY($1)
+  AND i.indisvalid
+  AND NOT EXISTS (
+    SELECT 1 FROM pg_catalog.pg_constraint AS con WHERE con.conindid = i.indexrelid
+  )
+ORDER BY ns.nspname, t.relname, ic.relname;
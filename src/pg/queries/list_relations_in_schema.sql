@@ -1,12 +1,32 @@
 SELECT
   c.oid
+  , n.nspname AS schema
   , c.relname AS name
   , c.relkind::TEXT
   , c.reltuples::BIGINT AS approx_n_rows
+  , c.relispopulated AS populated -- false for materialized views created WITH NO DATA
+  , CASE WHEN c.relispartition THEN (
+      -- the topmost partitioned table above it, like postgres 12's pg_partition_root
+      WITH RECURSIVE ancestor (oid, relispartition) AS (
+          SELECT p.oid, p.relispartition
+          FROM pg_catalog.pg_inherits AS i
+              JOIN pg_catalog.pg_class AS p ON p.oid = i.inhparent
+          WHERE i.inhrelid = c.oid
+        UNION ALL
+          SELECT p.oid, p.relispartition
+          FROM ancestor AS a
+              JOIN pg_catalog.pg_inherits AS i ON i.inhrelid = a.oid
+              JOIN pg_catalog.pg_class AS p ON p.oid = i.inhparent
+          WHERE a.relispartition
+      )
+      SELECT oid FROM ancestor WHERE NOT relispartition
+    ) END AS partition_root
+  , NOT c.relispartition AND EXISTS (
+      SELECT 1 FROM pg_catalog.pg_inherits AS i WHERE i.inhrelid = c.oid
+    ) AS inherits -- from another table, with INHERITS
   , pg_catalog.pg_get_userbyid(c.relowner) as owner
 FROM pg_catalog.pg_class c
     LEFT JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
-WHERE c.relkind IN ('r','v','m','s','p')
-    AND n.nspname = $1
-    AND pg_catalog.pg_table_is_visible(c.oid)
+WHERE c.relkind IN ('r','v','m','s','p','f')
+    AND n.nspname = ANY($1)
 ORDER BY 1, 2;
@@ -1,18 +1,40 @@
+-- pg_attribute rather than information_schema.columns, which leaves out materialized views
 SELECT
-  col.column_name
-  , col.ordinal_position
-  , col.table_name
-  , col.udt_name
-  , col.is_nullable
-  , col.character_maximum_length
-  , col.character_octet_length
-  , col.numeric_precision
-  , col.numeric_precision_radix
-  , col.numeric_scale
-  , col.datetime_precision
-  , col.interval_type
-  , col.interval_precision
-FROM information_schema.columns col
-WHERE col.table_schema = $1 AND col.table_name = ANY($2)
+  a.attname::TEXT AS column_name
+  , a.attnum AS ordinal_position
+  , ns.nspname::TEXT AS table_schema
+  , c.relname::TEXT AS table_name
+  , COALESCE(base.typname, t.typname)::TEXT AS udt_name -- a domain's base type
+  -- of a NUMERIC(precision, scale) column, or a domain over one; NULL for a bare NUMERIC
+  , CASE WHEN COALESCE(base.typname, t.typname) = 'numeric' AND typmod.n >= 0
+      THEN ((typmod.n - 4) >> 16) & 65535
+    END AS numeric_precision
+  , CASE WHEN COALESCE(base.typname, t.typname) = 'numeric' AND typmod.n >= 0
+      THEN (((typmod.n - 4) & 2047) # 1024) - 1024 -- negative since postgres 15
+    END AS numeric_scale
+  , CASE WHEN a.attnotnull THEN 'NO' ELSE 'YES' END AS is_nullable
+  -- no default for a generated column; attgenerated is only there since postgres 12
+  , CASE WHEN COALESCE(to_jsonb(a) ->> 'attgenerated', '') = ''
+      THEN pg_get_expr(def.adbin, def.adrelid)
+    END AS column_default
+  -- the sequence a serial or identity column owns, and its last value, if any
+  , pg_get_serial_sequence(format('%I.%I', ns.nspname, c.relname), a.attname)
+    IS NOT NULL AS is_serial
+  , pg_sequence_last_value(
+      pg_get_serial_sequence(format('%I.%I', ns.nspname, c.relname), a.attname)::REGCLASS
+    ) AS sequence_last_value
+FROM pg_catalog.pg_attribute AS a
+  JOIN pg_catalog.pg_class AS c ON c.oid = a.attrelid
+  JOIN pg_catalog.pg_namespace AS ns ON ns.oid = c.relnamespace
+  JOIN pg_catalog.pg_type AS t ON t.oid = a.atttypid
+  LEFT JOIN pg_catalog.pg_type AS base ON base.oid = t.typbasetype AND t.typtype = 'd'
+  LEFT JOIN pg_catalog.pg_attrdef AS def ON def.adrelid = a.attrelid AND def.adnum = a.attnum
+  CROSS JOIN LATERAL (
+    SELECT CASE WHEN a.atttypmod >= 0 THEN a.atttypmod ELSE t.typtypmod END AS n
+  ) AS typmod
+WHERE a.attnum > 0
+  AND NOT a.attisdropped
+  AND (ns.nspname, c.relname) IN (
+    SELECT * FROM unnest($1::TEXT[], $2::TEXT[])
+  )
 ORDER BY 2
-        
\ No newline at end of file
@@ -1,8 +1,124 @@
-pub fn connect(connection_string: &str) -> postgres::Client {
-    match postgres::Client::connect(connection_string, postgres::NoTls) {
-        Ok(conn) => return conn,
-        Err(e) => panic!("{:?}", e),
+use super::conninfo::{self, ConnParams};
+use super::tls::{self, SslMode};
+use crate::quote;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ConnectError {
+    /// the connection parameters were missing or invalid
+    Config(String),
+    /// a certificate or key couldn't be loaded into the TLS connector
+    Tls(openssl::error::ErrorStack),
+    /// the server's certificate didn't pass the checks `sslmode` asked for
+    CertificateVerification {
+        host: String,
+        reason: String,
+    },
+    Postgres(postgres::Error),
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectError::Config(msg) => write!(f, "invalid connection parameters: {}", msg),
+            ConnectError::Tls(e) => write!(f, "unable to configure TLS: {}", e),
+            ConnectError::CertificateVerification { host, reason } => {
+                write!(
+                    f,
+                    "certificate verification failed for {}: {}",
+                    host, reason
+                )
+            }
+            ConnectError::Postgres(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConnectError {}
+
+impl From<openssl::error::ErrorStack> for ConnectError {
+    fn from(e: openssl::error::ErrorStack) -> Self {
+        return ConnectError::Tls(e);
+    }
+}
+
+/// Connect using `src` (if any) plus the libpq environment and config files;
+/// see the `conninfo` module for the order of precedence. `overrides` are
+/// parameters passed as their own CLI flags.
+pub fn connect(
+    src: Option<&str>,
+    overrides: &ConnParams,
+) -> Result<postgres::Client, ConnectError> {
+    let mut params = conninfo::resolve(src).map_err(ConnectError::Config)?;
+    for (key, value) in overrides {
+        params.insert(key.to_owned(), value.to_owned());
     }
+    let config = conninfo::to_config(&params).map_err(ConnectError::Config)?;
+    let mode = SslMode::parse(&params["sslmode"]).map_err(ConnectError::Config)?;
+    if mode == SslMode::Disable {
+        return config
+            .connect(postgres::NoTls)
+            .map_err(ConnectError::Postgres);
+    }
+    let (connector, failure) = tls::make_connector(mode, &params)?;
+    match config.connect(connector) {
+        Ok(conn) => return Ok(conn),
+        Err(e) => match failure.lock().unwrap().take() {
+            Some(reason) => {
+                return Err(ConnectError::CertificateVerification {
+                    host: params
+                        .get("host")
+                        .or_else(|| params.get("hostaddr"))
+                        .cloned()
+                        .unwrap_or_default(),
+                    reason,
+                })
+            }
+            None => return Err(ConnectError::Postgres(e)),
+        },
+    }
+}
+
+/// Start the transaction that every introspection query and table read runs
+/// in, so the copy reflects a single point in time. (Postgres ignores
+/// `DEFERRABLE` below `SERIALIZABLE`; it's there to mark the intent.)
+pub fn begin_snapshot(
+    conn: &mut postgres::Client,
+) -> Result<postgres::Transaction<'_>, postgres::Error> {
+    return conn
+        .build_transaction()
+        .isolation_level(postgres::IsolationLevel::RepeatableRead)
+        .read_only(true)
+        .deferrable(true)
+        .start();
+}
+
+/// Set the time zone of `conn`'s session, which TIMESTAMPTZ values are
+/// formatted in. The driver starts every session in UTC, whatever `TimeZone`
+/// the server or the connection string asks for.
+pub fn set_time_zone(conn: &mut postgres::Client, zone: &str) -> Result<(), postgres::Error> {
+    return conn.batch_execute(&format!("SET TIME ZONE {}", quote::literal(zone)));
+}
+
+/// Name the snapshot of `txn` so other sessions can read from it, too.
+/// The name is valid until `txn` ends.
+pub fn export_snapshot(txn: &mut postgres::Transaction) -> Result<String, postgres::Error> {
+    let row = txn.query_one("SELECT pg_export_snapshot()", &[])?;
+    return Ok(row.get(0));
+}
+
+/// Like `begin_snapshot`, but sees the same data as the transaction that
+/// exported `snapshot`.
+pub fn import_snapshot<'a>(
+    conn: &'a mut postgres::Client,
+    snapshot: &str,
+) -> Result<postgres::Transaction<'a>, postgres::Error> {
+    let mut txn = begin_snapshot(conn)?;
+    txn.batch_execute(&format!(
+        "SET TRANSACTION SNAPSHOT {}",
+        quote::literal(snapshot)
+    ))?;
+    return Ok(txn);
 }
 
 pub fn must_succeed(response: Result<Vec<postgres::Row>, postgres::Error>) -> Vec<postgres::Row> {
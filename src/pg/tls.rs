@@ -0,0 +1,166 @@
+//! TLS for the source connection, following libpq's `sslmode` semantics:
+//! https://www.postgresql.org/docs/current/libpq-ssl.html#LIBPQ-SSL-SSLMODE-STATEMENTS
+
+use super::conninfo::{self, ConnParams};
+use super::query::ConnectError;
+use openssl::ssl::{SslConnector, SslFiletype, SslMethod, SslVerifyMode};
+use openssl::x509::verify::X509CheckFlags;
+use postgres_openssl::MakeTlsConnector;
+use std::{
+    env,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SslMode {
+    Disable,
+    Allow,
+    Prefer,
+    Require,
+    VerifyCa,
+    VerifyFull,
+}
+
+impl SslMode {
+    pub fn parse(mode: &str) -> Result<SslMode, String> {
+        match mode {
+            "disable" => Ok(SslMode::Disable),
+            "allow" => Ok(SslMode::Allow),
+            "prefer" => Ok(SslMode::Prefer),
+            "require" => Ok(SslMode::Require),
+            "verify-ca" => Ok(SslMode::VerifyCa),
+            "verify-full" => Ok(SslMode::VerifyFull),
+            other => Err(format!("invalid sslmode \"{}\"", other)),
+        }
+    }
+    /// The closest mode the postgres crate can negotiate. It only decides
+    /// whether TLS is attempted; certificate checks are up to the connector.
+    /// `allow` is treated as `prefer`, since non-TLS connections aren't retried.
+    pub fn negotiation(self) -> postgres::config::SslMode {
+        match self {
+            SslMode::Disable => postgres::config::SslMode::Disable,
+            SslMode::Allow | SslMode::Prefer => postgres::config::SslMode::Prefer,
+            _ => postgres::config::SslMode::Require,
+        }
+    }
+}
+
+pub const SSL_MODES: &[&str] = &[
+    "disable",
+    "allow",
+    "prefer",
+    "require",
+    "verify-ca",
+    "verify-full",
+];
+
+/// The first certificate verification failure seen during a handshake.
+pub type VerifyFailure = Arc<Mutex<Option<String>>>;
+
+/// libpq looks for certificates in `~/.postgresql` when none are named.
+fn default_cert_path(file: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(env::var_os("HOME")?)
+        .join(".postgresql")
+        .join(file);
+    if path.is_file() {
+        return Some(path);
+    } else {
+        return None;
+    }
+}
+
+fn cert_path(params: &ConnParams, key: &str, default_file: &str) -> Option<PathBuf> {
+    match params.get(key) {
+        Some(path) => Some(PathBuf::from(path)),
+        None => default_cert_path(default_file),
+    }
+}
+
+/// Build a connector that checks the server the way `sslmode` asks.
+pub fn make_connector(
+    mode: SslMode,
+    params: &ConnParams,
+) -> Result<(MakeTlsConnector, VerifyFailure), ConnectError> {
+    let mut builder = SslConnector::builder(SslMethod::tls())?;
+
+    let rootcert = cert_path(params, "sslrootcert", "root.crt");
+    match &rootcert {
+        Some(path) if path.to_str() == Some("system") => builder.set_default_verify_paths()?,
+        Some(path) if path.is_file() => builder.set_ca_file(path)?,
+        Some(path) if mode == SslMode::VerifyCa || mode == SslMode::VerifyFull => {
+            return Err(ConnectError::Config(format!(
+                "root certificate file \"{}\" does not exist",
+                path.display()
+            )));
+        }
+        None if mode == SslMode::VerifyCa || mode == SslMode::VerifyFull => {
+            return Err(ConnectError::Config(
+                "certificate verification requires a root certificate: pass sslrootcert".to_owned(),
+            ));
+        }
+        _ => {}
+    }
+    let has_rootcert = rootcert.is_some_and(|p| p.to_str() == Some("system") || p.is_file());
+
+    let cert = cert_path(params, "sslcert", "postgresql.crt");
+    let key = cert_path(params, "sslkey", "postgresql.key");
+    if let Some(cert) = cert.filter(|c| c.is_file()) {
+        builder.set_certificate_chain_file(&cert)?;
+        match key {
+            Some(key) => builder.set_private_key_file(&key, SslFiletype::PEM)?,
+            None => {
+                return Err(ConnectError::Config(format!(
+                    "certificate \"{}\" is present, but there's no sslkey",
+                    cert.display()
+                )))
+            }
+        }
+    }
+
+    // like libpq, `require` checks the certificate chain only if a root
+    // certificate is available, and only `verify-full` checks the hostname.
+    let verify_chain = match mode {
+        SslMode::VerifyCa | SslMode::VerifyFull => true,
+        SslMode::Require => has_rootcert,
+        _ => false,
+    };
+    let failure: VerifyFailure = Arc::new(Mutex::new(None));
+    if verify_chain {
+        let seen = failure.clone();
+        builder.set_verify_callback(SslVerifyMode::PEER, move |ok, ctx| {
+            if !ok {
+                let mut seen = seen.lock().unwrap();
+                if seen.is_none() {
+                    *seen = Some(ctx.error().error_string().to_owned());
+                }
+            }
+            ok
+        });
+    } else {
+        builder.set_verify(SslVerifyMode::NONE);
+    }
+
+    let mut connector = MakeTlsConnector::new(builder.build());
+    let names = conninfo::certificate_names(params);
+    if mode != SslMode::VerifyFull {
+        connector.set_callback(|config, _| {
+            config.set_verify_hostname(false);
+            Ok(())
+        });
+    } else if !names.is_empty() {
+        // the connection is to a `hostaddr`, but libpq checks the certificate
+        // against the `host` beside it, which it also sends for SNI
+        connector.set_callback(move |config, addr| {
+            if let Some((_, host)) = names.iter().find(|(a, _)| a == addr) {
+                config.set_verify_hostname(false);
+                config.set_hostname(host)?;
+                let param = config.param_mut();
+                param.set_hostflags(X509CheckFlags::NO_PARTIAL_WILDCARDS);
+                param.set_host(host)?;
+            }
+            Ok(())
+        });
+    }
+    return Ok((connector, failure));
+}
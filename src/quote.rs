@@ -0,0 +1,160 @@
+//! Quote identifiers for the SQL sent to postgres and written to SQLite.
+//!
+//! Both sides leave an identifier bare when it reads the same bare, and
+//! otherwise double-quote it, doubling any `"` inside.
+
+/// postgres' keywords that can't be used bare as a column or table name, i.e.
+/// `SELECT word FROM pg_get_keywords() WHERE catcode <> 'U'` (postgres 15).
+const PG_KEYWORDS: &str =
+    "all analyse analyze and any array as asc asymmetric authorization between bigint binary \
+    bit boolean both case cast char character check coalesce collate collation column \
+    concurrently constraint create cross current_catalog current_date current_role \
+    current_schema current_time current_timestamp current_user dec decimal default deferrable \
+    desc distinct do else end except exists extract false fetch float for foreign freeze from \
+    full grant greatest group grouping having ilike in initially inner inout int integer \
+    intersect interval into is isnull join lateral leading least left like limit localtime \
+    localtimestamp national natural nchar none normalize not notnull null nullif numeric \
+    offset on only or order out outer overlaps overlay placing position precision primary real \
+    references returning right row select session_user setof similar smallint some substring \
+    symmetric table tablesample then time timestamp to trailing treat trim true union unique \
+    user using values varchar variadic verbose when where window with xmlattributes xmlconcat \
+    xmlelement xmlexists xmlforest xmlnamespaces xmlparse xmlpi xmlroot xmlserialize xmltable";
+
+/// SQLite's keywords; https://www.sqlite.org/lang_keywords.html
+const SQLITE_KEYWORDS: &str =
+    "ABORT ACTION ADD AFTER ALL ALTER ALWAYS ANALYZE AND AS ASC ATTACH AUTOINCREMENT BEFORE \
+    BEGIN BETWEEN BY CASCADE CASE CAST CHECK COLLATE COLUMN COMMIT CONFLICT CONSTRAINT CREATE \
+    CROSS CURRENT CURRENT_DATE CURRENT_TIME CURRENT_TIMESTAMP DATABASE DEFAULT DEFERRABLE \
+    DEFERRED DELETE DESC DETACH DISTINCT DO DROP EACH ELSE END ESCAPE EXCEPT EXCLUDE EXCLUSIVE \
+    EXISTS EXPLAIN FAIL FILTER FIRST FOLLOWING FOR FOREIGN FROM FULL GENERATED GLOB GROUP \
+    GROUPS HAVING IF IGNORE IMMEDIATE IN INDEX INDEXED INITIALLY INNER INSERT INSTEAD \
+    INTERSECT INTO IS ISNULL JOIN KEY LAST LEFT LIKE LIMIT MATCH MATERIALIZED NATURAL NO NOT \
+    NOTHING NOTNULL NULL NULLS OF OFFSET ON OR ORDER OTHERS OUTER OVER PARTITION PLAN PRAGMA \
+    PRECEDING PRIMARY QUERY RAISE RANGE RECURSIVE REFERENCES REGEXP REINDEX RELEASE RENAME \
+    REPLACE RESTRICT RETURNING RIGHT ROLLBACK ROW ROWS SAVEPOINT SELECT SET TABLE TEMP \
+    TEMPORARY THEN TIES TO TRANSACTION TRIGGER UNBOUNDED UNION UNIQUE UPDATE USING VACUUM \
+    VALUES VIEW VIRTUAL WHEN WHERE WINDOW WITH WITHOUT";
+
+fn double_quote(ident: &str) -> String {
+    return format!("\"{}\"", ident.replace('"', "\"\""));
+}
+
+/// Whether `word` is one of postgres' keywords that `quote_ident` quotes, so
+/// postgres never prints it bare as a name.
+pub fn pg_keyword(word: &str) -> bool {
+    return PG_KEYWORDS
+        .split_whitespace()
+        .any(|k| k.eq_ignore_ascii_case(word));
+}
+
+/// `ident` as postgres' `quote_ident` would write it: bare if it's all
+/// lowercase letters, digits, and underscores, doesn't start with a digit,
+/// and isn't a reserved keyword, since postgres folds bare names to lowercase.
+pub fn pg(ident: &str) -> String {
+    let mut chars = ident.chars();
+    let plain = match chars.next() {
+        Some(c) => c.is_ascii_lowercase() || c == '_',
+        None => false,
+    } && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if plain && !pg_keyword(ident) {
+        return ident.to_owned();
+    }
+    return double_quote(ident);
+}
+
+/// `ident` for SQLite: bare if it's ASCII letters, digits, and underscores,
+/// doesn't start with a digit, and isn't a keyword. SQLite matches names
+/// case-insensitively, so case alone doesn't call for quotes.
+pub fn sqlite(ident: &str) -> String {
+    let mut chars = ident.chars();
+    let plain = match chars.next() {
+        Some(c) => c.is_ascii_alphabetic() || c == '_',
+        None => false,
+    } && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let keyword = SQLITE_KEYWORDS
+        .split_whitespace()
+        .any(|k| k.eq_ignore_ascii_case(ident));
+    if plain && !keyword {
+        return ident.to_owned();
+    }
+    return double_quote(ident);
+}
+
+/// `name` in SQLite database `schema`, e.g. an attached one; `main` needs no
+/// qualifying.
+pub fn sqlite_qualified(schema: Option<&str>, name: &str) -> String {
+    return match schema {
+        Some(schema) => format!("{}.{}", sqlite(schema), sqlite(name)),
+        None => sqlite(name),
+    };
+}
+
+/// `value` as a SQL string literal, which is written the same on both sides.
+pub fn literal(value: &str) -> String {
+    return format!("'{}'", value.replace('\'', "''"));
+}
+
+#[test]
+fn test_quoting_identifiers() {
+    assert_eq!(pg("users"), "users");
+    assert_eq!(pg("user"), "\"user\"");
+    assert_eq!(pg("Users"), "\"Users\"");
+    assert_eq!(pg("order items"), "\"order items\"");
+    assert_eq!(pg("2fa"), "\"2fa\"");
+    assert_eq!(pg("say \"hi\""), "\"say \"\"hi\"\"\"");
+    assert_eq!(pg("name"), "name"); // an unreserved keyword
+    assert_eq!(sqlite("Users"), "Users");
+    assert_eq!(sqlite("group"), "\"group\"");
+    assert_eq!(sqlite("Group"), "\"Group\"");
+    assert_eq!(sqlite("café"), "\"café\"");
+    assert_eq!(sqlite(""), "\"\"");
+    assert_eq!(sqlite_qualified(Some("audit log"), "t"), "\"audit log\".t");
+    assert_eq!(literal("it's"), "'it''s'");
+}
+
+#[cfg(test)]
+proptest::proptest! {
+    #[test]
+    fn test_pg_quoting_preserves_identifiers(ident in "[^\u{0}]{1,20}") {
+        let quoted = pg(&ident);
+        if quoted == ident {
+            // postgres would fold anything but lowercase to lowercase
+            proptest::prop_assert_eq!(ident.to_lowercase(), ident.clone());
+            proptest::prop_assert!(!ident.contains(|c: char| c == '"' || c.is_whitespace()));
+        } else {
+            let inner = &quoted[1..quoted.len() - 1];
+            proptest::prop_assert_eq!(inner.replace("\"\"", "\""), ident);
+        }
+    }
+
+    #[test]
+    fn test_sqlite_quoting_round_trips(
+        table in "[^\u{0}]{0,20}",
+        column in "[^\u{0}]{0,20}",
+    ) {
+        // SQLite reserves these names for its own tables
+        proptest::prop_assume!(!table.to_ascii_lowercase().starts_with("sqlite_"));
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(&format!(
+            "CREATE TABLE {0} ({1} TEXT); INSERT INTO {0} ({1}) VALUES ('x');",
+            sqlite(&table),
+            sqlite(&column)
+        ))
+        .unwrap();
+        let (name, col, value): (String, String, String) = conn
+            .query_row(
+                &format!(
+                    "SELECT m.name, c.name, t.{} FROM sqlite_master AS m, \
+                    pragma_table_info(m.name) AS c, {} AS t",
+                    sqlite(&column),
+                    sqlite(&table)
+                ),
+                rusqlite::NO_PARAMS,
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        proptest::prop_assert_eq!(name, table);
+        proptest::prop_assert_eq!(col, column);
+        proptest::prop_assert_eq!(value, "x");
+    }
+}
@@ -1,9 +1,263 @@
 // rustqlite goes here!
 // fn dump_table_data(row_defn, rows) {}
 // fn copy_view(v) {}
-// reinstate indices
 // fn reinstate_constraint(c) {}
-use rusqlite::{Connection, Error};
+use crate::quote;
+use rusqlite::{Connection, DatabaseName, Error};
+use std::{fs, io, path::Path, process};
+
+/// Pragmas for writing quickly while DEST is being built. With the journal
+/// off, a crash mid-load can corrupt the database, so `LoadProfile::finish`
+/// switches to durable settings once every row is in.
+pub const LOAD_PRAGMAS: &[(&str, &str)] = &[
+    ("page_size", "8192"), // must be set before any table is created
+    ("journal_mode", "OFF"),
+    ("synchronous", "OFF"),
+    ("cache_size", "-262144"), // in KiB, so 256 MiB
+    ("temp_store", "MEMORY"),
+    ("locking_mode", "EXCLUSIVE"),
+    ("mmap_size", "268435456"),
+];
+
+/// Pragmas that apply to the connection rather than to one database.
+const CONNECTION_PRAGMAS: &[&str] = &["temp_store"];
+
+pub const FINAL_JOURNAL_MODES: &[&str] = &["delete", "wal"];
+
+/// Parse a `--pragma name=value` override of one of the `LOAD_PRAGMAS`.
+pub fn parse_pragma(arg: &str) -> Result<(String, String), String> {
+    let (name, value) = match arg.find('=') {
+        Some(i) => (arg[..i].trim(), arg[i + 1..].trim()),
+        None => return Err(format!("expected NAME=VALUE, got {:?}", arg)),
+    };
+    if !LOAD_PRAGMAS.iter().any(|(n, _)| *n == name) {
+        let names: Vec<&str> = LOAD_PRAGMAS.iter().map(|(n, _)| *n).collect();
+        return Err(format!(
+            "{:?} is not one of the load pragmas: {}",
+            name,
+            names.join(", ")
+        ));
+    }
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("invalid value {:?} for pragma {}", value, name));
+    }
+    return Ok((name.to_owned(), value.to_owned()));
+}
+
+pub struct LoadProfile {
+    pragmas: Vec<(String, String)>,
+    final_journal_mode: String,
+}
+
+impl LoadProfile {
+    /// `LOAD_PRAGMAS`, with any of them replaced by `overrides`.
+    pub fn new(overrides: &[(String, String)], final_journal_mode: &str) -> LoadProfile {
+        let mut pragmas: Vec<(String, String)> = LOAD_PRAGMAS
+            .iter()
+            .map(|(name, value)| ((*name).to_owned(), (*value).to_owned()))
+            .collect();
+        for (name, value) in overrides {
+            match pragmas.iter_mut().find(|(n, _)| n == name) {
+                Some(pragma) => pragma.1 = value.to_owned(),
+                None => panic!("{} is not a load pragma", name),
+            }
+        }
+        return LoadProfile {
+            pragmas,
+            final_journal_mode: final_journal_mode.to_owned(),
+        };
+    }
+
+    fn set(conn: &Connection, schemas: &[String], name: &str, value: &str) -> Result<(), Error> {
+        if CONNECTION_PRAGMAS.contains(&name) {
+            return conn.pragma_update(None, name, &value);
+        }
+        conn.pragma_update(Some(DatabaseName::Main), name, &value)?;
+        for schema in schemas {
+            conn.pragma_update(Some(DatabaseName::Attached(schema)), name, &value)?;
+        }
+        return Ok(());
+    }
+
+    /// Apply the load pragmas to the main database and each attached schema.
+    pub fn apply(&self, conn: &Connection, schemas: &[String]) -> Result<(), Error> {
+        for (name, value) in &self.pragmas {
+            LoadProfile::set(conn, schemas, name, value)?;
+        }
+        return Ok(());
+    }
+
+    /// Switch to durable settings, release the exclusive lock, and gather
+    /// statistics for the query planner.
+    pub fn finish(&self, conn: &Connection, schemas: &[String]) -> Result<(), Error> {
+        LoadProfile::set(conn, schemas, "synchronous", "NORMAL")?;
+        LoadProfile::set(conn, schemas, "journal_mode", &self.final_journal_mode)?;
+        LoadProfile::set(conn, schemas, "locking_mode", "NORMAL")?;
+        conn.execute_batch("ANALYZE")?;
+        return Ok(());
+    }
+}
+
+/// Hidden files next to DEST (and next to each attached database) that are
+/// written instead, and only renamed over their destinations once the whole
+/// load has succeeded. If the load fails or panics, they're deleted.
+pub struct Staging {
+    /// `(staged path, final path)`, with DEST's first
+    files: Vec<(String, String)>,
+    /// `(schema, staged path)` of each attached database
+    attachments: Vec<(String, String)>,
+    committed: bool,
+}
+
+fn staged_path(path: &str) -> String {
+    let path = Path::new(path);
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("db");
+    let staged = path.with_file_name(format!(".{}.{}.tmp", name, process::id()));
+    return staged.to_string_lossy().into_owned();
+}
+
+fn remove_if_present(path: &str) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => return Ok(()),
+    }
+}
+
+/// Make a file just renamed to `path` survive a crash, by syncing the
+/// directory it's in. Only unix can open a directory to sync it.
+#[cfg(unix)]
+fn sync_parent(path: &str) -> io::Result<()> {
+    let parent = match Path::new(path).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    return fs::File::open(parent)?.sync_all();
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_: &str) -> io::Result<()> {
+    return Ok(());
+}
+
+impl Staging {
+    /// With `copy_existing`, staged files start as copies of their
+    /// destinations, for adding rows to tables that are already there.
+    pub fn new(
+        dest: &str,
+        attachments: &[(String, String)],
+        copy_existing: bool,
+    ) -> io::Result<Staging> {
+        let mut files = vec![(staged_path(dest), dest.to_owned())];
+        let mut staged_attachments = vec![];
+        for (schema, path) in attachments {
+            files.push((staged_path(path), path.to_owned()));
+            staged_attachments.push((schema.to_owned(), staged_path(path)));
+        }
+        for (staged, dest) in &files {
+            remove_if_present(staged)?; // left by an earlier process with the same id
+            if copy_existing && Path::new(dest).is_file() {
+                fs::copy(dest, staged)?;
+            }
+        }
+        return Ok(Staging {
+            files,
+            attachments: staged_attachments,
+            committed: false,
+        });
+    }
+
+    /// Where to open DEST.
+    pub fn path(&self) -> &str {
+        return &self.files[0].0;
+    }
+
+    pub fn attachments(&self) -> &[(String, String)] {
+        return &self.attachments;
+    }
+
+    /// Move every staged file into place, attached databases first. Close
+    /// the connection first, so nothing is left in a journal. Each file is
+    /// synced before it's renamed, and its directory after, so a crash
+    /// leaves either the old DEST or the whole new one.
+    pub fn commit(mut self) -> io::Result<()> {
+        for (staged, _) in &self.files {
+            fs::OpenOptions::new()
+                .write(true)
+                .open(staged)?
+                .sync_all()?;
+        }
+        for (staged, dest) in self.files.iter().rev() {
+            fs::rename(staged, dest)?;
+            sync_parent(dest)?;
+        }
+        self.committed = true;
+        return Ok(());
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+        for (staged, _) in &self.files {
+            for suffix in &["", "-journal", "-wal", "-shm"] {
+                let _ = remove_if_present(&format!("{}{}", staged, suffix)); // best effort
+            }
+        }
+    }
+}
+
+/// Problems `PRAGMA integrity_check` finds in the main database or in any of
+/// the attached `schemas`.
+pub fn check_integrity(conn: &Connection, schemas: &[String]) -> Result<Vec<String>, Error> {
+    let mut problems = vec![];
+    let main = "main".to_owned();
+    for schema in std::iter::once(&main).chain(schemas) {
+        let mut check =
+            conn.prepare(&format!("PRAGMA {}.integrity_check", quote::sqlite(schema)))?;
+        let results = check.query_map(rusqlite::NO_PARAMS, |row| row.get::<usize, String>(0))?;
+        for result in results {
+            let result = result?;
+            if result != "ok" {
+                problems.push(format!("{}: {}", schema, result));
+            }
+        }
+    }
+    return Ok(problems);
+}
+
+/// Rows that reference missing rows, as found by each of `checks`, a
+/// `PRAGMA foreign_key_check` statement.
+pub fn check_foreign_keys(conn: &Connection, checks: &[String]) -> Result<Vec<String>, Error> {
+    let mut problems = vec![];
+    for check in checks {
+        let mut check = conn.prepare(check)?;
+        let results = check.query_map(rusqlite::NO_PARAMS, |row| {
+            return Ok(format!(
+                "row {} of {} references a missing row of {}",
+                row.get::<usize, i64>(1)?,
+                row.get::<usize, String>(0)?,
+                row.get::<usize, String>(2)?
+            ));
+        })?;
+        for result in results {
+            problems.push(result?);
+        }
+    }
+    return Ok(problems);
+}
+
+/// Attach a database file for each `(schema, path)`.
+pub fn attach_databases(conn: &Connection, attachments: &[(String, String)]) -> Result<(), Error> {
+    for (schema, path) in attachments {
+        conn.execute(
+            &format!("ATTACH DATABASE ?1 AS {}", quote::sqlite(schema)),
+            &[path],
+        )?;
+    }
+    return Ok(());
+}
 
 pub fn create_all_tables(conn: &mut Connection, create_table_stmt: &str) -> Result<(), Error> {
     let txn = conn.transaction()?;
@@ -12,6 +266,12 @@ pub fn create_all_tables(conn: &mut Connection, create_table_stmt: &str) -> Resu
     return result;
 }
 
+pub fn create_all_indexes(conn: &mut Connection, create_index_stmts: &str) -> Result<(), Error> {
+    let txn = conn.transaction()?;
+    txn.execute_batch(create_index_stmts)?;
+    return txn.commit();
+}
+
 pub fn create_all_views(conn: &mut Connection, view_defns: &str) -> Result<(), Error> {
     let result = conn.execute_batch(view_defns);
     match result {
@@ -22,3 +282,26 @@ pub fn create_all_views(conn: &mut Connection, view_defns: &str) -> Result<(), E
         }
     }
 }
+
+#[test]
+fn test_staging_only_replaces_dest_on_commit() {
+    let dir = std::env::temp_dir().join(format!("pg-to-sqlite3-staging-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let dest = dir.join("out.db").to_string_lossy().into_owned();
+    fs::write(&dest, "old").unwrap();
+
+    let failed = Staging::new(&dest, &[], false).unwrap();
+    fs::write(failed.path(), "partial").unwrap();
+    let staged = failed.path().to_owned();
+    drop(failed);
+    assert!(!Path::new(&staged).exists());
+    assert_eq!(fs::read_to_string(&dest).unwrap(), "old");
+
+    let appended = Staging::new(&dest, &[], true).unwrap();
+    assert_eq!(fs::read_to_string(appended.path()).unwrap(), "old");
+    fs::write(appended.path(), "new").unwrap();
+    appended.commit().unwrap();
+    assert_eq!(fs::read_to_string(&dest).unwrap(), "new");
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(sync_parent("out.db").is_ok()); // in the working directory
+}
@@ -0,0 +1,109 @@
+//! These tests need the `fs_db` docker-compose service; see scripts/test_fixtures.sh
+mod common;
+use common::copy;
+use rusqlite::types::Value;
+
+fn rows(conn: &rusqlite::Connection, sql: &str) -> Vec<Vec<Value>> {
+    let mut statement = conn.prepare(sql).unwrap();
+    let n = statement.column_count();
+    return statement
+        .query_map(rusqlite::NO_PARAMS, |row| {
+            return (0..n).map(|i| row.get(i)).collect();
+        })
+        .unwrap()
+        .map(|row| row.unwrap())
+        .collect();
+}
+
+fn text(value: &str) -> Value {
+    return Value::Text(value.to_owned());
+}
+
+#[test]
+#[ignore]
+fn test_copying_arrays_as_json() {
+    let conn = copy("arrays_json", &[]);
+    assert_eq!(
+        rows(&conn, "SELECT tags, scores FROM survey ORDER BY id"),
+        vec![
+            vec![text(r#"["a","b"]"#), text("[[1,2],[3,null]]")],
+            vec![text("[]"), Value::Null],
+            vec![text(r#"["with, comma",null]"#), text("[7]")],
+        ]
+    );
+    assert_eq!(
+        rows(
+            &conn,
+            "SELECT json_extract(scores, '$[1][0]') FROM survey WHERE id = 1"
+        ),
+        vec![vec![Value::Integer(3)]]
+    );
+    conn.execute("INSERT INTO survey (id) VALUES (4)", rusqlite::NO_PARAMS)
+        .unwrap();
+    assert_eq!(
+        rows(&conn, "SELECT tags FROM survey WHERE id = 4"),
+        vec![vec![text("[]")]]
+    );
+}
+
+#[test]
+#[ignore]
+fn test_copying_arrays_as_tables() {
+    let conn = copy("arrays_table", &["--arrays", "table"]);
+    assert_eq!(
+        rows(&conn, "SELECT name FROM pragma_table_info('survey')"),
+        vec![vec![text("id")]]
+    );
+    assert_eq!(
+        rows(
+            &conn,
+            "SELECT id, ordinal, value FROM survey_tags ORDER BY id, ordinal"
+        ),
+        vec![
+            vec![Value::Integer(1), Value::Integer(1), text("a")],
+            vec![Value::Integer(1), Value::Integer(2), text("b")],
+            vec![Value::Integer(3), Value::Integer(1), text("with, comma")],
+            vec![Value::Integer(3), Value::Integer(2), Value::Null],
+        ]
+    );
+    assert_eq!(
+        rows(
+            &conn,
+            "SELECT ordinal, value FROM survey_scores WHERE id = 1 ORDER BY ordinal"
+        ),
+        vec![
+            vec![Value::Integer(1), Value::Integer(1)],
+            vec![Value::Integer(2), Value::Integer(2)],
+            vec![Value::Integer(3), Value::Integer(3)],
+            vec![Value::Integer(4), Value::Null],
+        ]
+    );
+    assert!(rows(&conn, "PRAGMA foreign_key_check").is_empty());
+    // deleting a row deletes its elements
+    conn.execute_batch("PRAGMA foreign_keys = ON; DELETE FROM survey WHERE id = 3")
+        .unwrap();
+    assert_eq!(
+        rows(&conn, "SELECT count(*) FROM survey_scores"),
+        vec![vec![Value::Integer(4)]]
+    );
+    // sample's own value and ordinal stay apart from its elements'
+    assert_eq!(
+        rows(&conn, "SELECT value, ordinal FROM sample"),
+        vec![vec![text("first"), Value::Integer(7)]]
+    );
+    assert_eq!(
+        rows(
+            &conn,
+            "SELECT ordinal, value FROM sample_readings ORDER BY ordinal"
+        ),
+        vec![
+            vec![Value::Integer(1), Value::Real(0.5)],
+            vec![Value::Integer(2), Value::Real(1.5)],
+        ]
+    );
+    // survey_log has no primary key, so its array stays JSON
+    assert_eq!(
+        rows(&conn, "SELECT answers FROM survey_log"),
+        vec![vec![text(r#"["yes","no"]"#)]]
+    );
+}
@@ -0,0 +1,157 @@
+//! These tests need the `fs_db` docker-compose service; see scripts/test_fixtures.sh
+mod common;
+use common::copy;
+
+/// Each row of `PRAGMA pragma(table)`, as text.
+fn pragma(conn: &rusqlite::Connection, pragma: &str, table: &str) -> Vec<Vec<String>> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA {}({})", pragma, table))
+        .unwrap();
+    let n = stmt.column_count();
+    return stmt
+        .query_map(rusqlite::NO_PARAMS, |row| {
+            return (0..n)
+                .map(|i| {
+                    let value: rusqlite::types::Value = row.get(i)?;
+                    return Ok(match value {
+                        rusqlite::types::Value::Text(s) => s,
+                        rusqlite::types::Value::Integer(i) => i.to_string(),
+                        other => format!("{:?}", other),
+                    });
+                })
+                .collect();
+        })
+        .unwrap()
+        .map(|row| row.unwrap())
+        .collect();
+}
+
+/// `(from, to)` column pairs of the foreign keys from `table` to `parent`.
+fn fkey_columns(conn: &rusqlite::Connection, table: &str, parent: &str) -> Vec<(String, String)> {
+    return pragma(conn, "foreign_key_list", table)
+        .into_iter()
+        .filter(|fk| fk[2] == parent)
+        .map(|fk| (fk[3].to_owned(), fk[4].to_owned()))
+        .collect();
+}
+
+/// Primary key columns of `table`, in key order.
+fn pkey_columns(conn: &rusqlite::Connection, table: &str) -> Vec<String> {
+    let mut columns: Vec<(String, String)> = pragma(conn, "table_info", table)
+        .into_iter()
+        .filter(|col| col[5] != "0")
+        .map(|col| (col[5].to_owned(), col[1].to_owned()))
+        .collect();
+    columns.sort();
+    return columns.into_iter().map(|(_, name)| name).collect();
+}
+
+/// Columns of each unique constraint on `table`, in key order.
+fn unique_columns(conn: &rusqlite::Connection, table: &str) -> Vec<Vec<String>> {
+    let mut uniques: Vec<Vec<String>> = pragma(conn, "index_list", table)
+        .into_iter()
+        .filter(|index| index[3] == "u")
+        .map(|index| {
+            return pragma(conn, "index_info", &index[1])
+                .into_iter()
+                .map(|col| col[2].to_owned())
+                .collect();
+        })
+        .collect();
+    uniques.sort();
+    return uniques;
+}
+
+fn pair(from: &str, to: &str) -> (String, String) {
+    return (from.to_owned(), to.to_owned());
+}
+
+#[test]
+#[ignore]
+fn test_composite_keys_keep_their_column_order() {
+    let conn = copy("composite_keys", &[]);
+    assert_eq!(
+        fkey_columns(&conn, "_download", "_release"),
+        vec![
+            pair("release_minor", "minor"),
+            pair("release_major", "major")
+        ]
+    );
+    assert_eq!(pkey_columns(&conn, "_release"), vec!["minor", "major"]);
+    assert_eq!(
+        unique_columns(&conn, "_release"),
+        vec![vec!["tarball_id", "major"]]
+    );
+}
+
+#[test]
+#[ignore]
+fn test_constraints_with_the_same_name_in_other_schemas() {
+    let conn = copy("same_names", &["--schema", "public,archive"]);
+    assert_eq!(
+        fkey_columns(&conn, "public___download", "public___release"),
+        vec![
+            pair("release_minor", "minor"),
+            pair("release_major", "major")
+        ]
+    );
+    assert_eq!(
+        fkey_columns(&conn, "archive___download", "archive___release"),
+        vec![pair("release_major", "major")]
+    );
+    assert_eq!(
+        pkey_columns(&conn, "public___release"),
+        vec!["minor", "major"]
+    );
+    assert_eq!(pkey_columns(&conn, "archive___release"), vec!["major"]);
+    assert_eq!(
+        unique_columns(&conn, "archive___release"),
+        vec![vec!["tarball_id"]]
+    );
+}
+
+#[test]
+#[ignore]
+fn test_foreign_key_actions() {
+    let conn = copy("fk_actions", &[]);
+    // (from, on_update, on_delete, match)
+    let mut actions: Vec<Vec<String>> = pragma(&conn, "foreign_key_list", "_file_tag")
+        .into_iter()
+        .map(|fk| {
+            vec![
+                fk[3].to_owned(),
+                fk[5].to_owned(),
+                fk[6].to_owned(),
+                fk[7].to_owned(),
+            ]
+        })
+        .collect();
+    actions.sort();
+    assert_eq!(
+        actions,
+        vec![
+            vec!["file_id", "RESTRICT", "CASCADE", "NONE"],
+            vec!["tag_id", "CASCADE", "SET NULL", "NONE"],
+            vec!["tagged_by", "NO ACTION", "SET DEFAULT", "NONE"],
+        ]
+    );
+    let ddl: String = conn
+        .query_row(
+            "SELECT sql FROM sqlite_master WHERE name = '_file_tag'",
+            rusqlite::NO_PARAMS,
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert!(
+        ddl.contains(
+            "MATCH FULL ON DELETE SET NULL ON UPDATE CASCADE DEFERRABLE INITIALLY DEFERRED"
+        ),
+        "{}",
+        ddl
+    );
+    assert!(
+        ddl.contains("ON DELETE SET DEFAULT DEFERRABLE INITIALLY IMMEDIATE"),
+        "{}",
+        ddl
+    );
+}
@@ -0,0 +1,21 @@
+//! These tests need the `fs_db` docker-compose service; see scripts/test_fixtures.sh
+mod common;
+use common::{copy, count};
+
+#[test]
+#[ignore]
+fn test_copying_foreign_key_cycles() {
+    let conn = copy("cycles", &[]);
+    assert_eq!(count(&conn, "_folder"), Some(6));
+    assert_eq!(count(&conn, "_user"), Some(3));
+    assert_eq!(count(&conn, "_team"), Some(2));
+    for table in &["_folder", "_user", "_team"] {
+        let violations = conn
+            .prepare(&format!("PRAGMA foreign_key_check({})", table))
+            .unwrap()
+            .query_map(rusqlite::NO_PARAMS, |row| row.get::<usize, String>(0))
+            .unwrap()
+            .count();
+        assert_eq!(violations, 0, "{}", table);
+    }
+}
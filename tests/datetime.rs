@@ -0,0 +1,251 @@
+//! These tests need the `fs_db` docker-compose service; see scripts/test_fixtures.sh
+mod common;
+use common::copy;
+use rusqlite::types::Value;
+
+/// `expr` for every shift, in id order.
+fn shifts(conn: &rusqlite::Connection, expr: &str) -> Vec<Value> {
+    return conn
+        .prepare(&format!("SELECT {} FROM shift ORDER BY id", expr))
+        .unwrap()
+        .query_map(rusqlite::NO_PARAMS, |row| row.get(0))
+        .unwrap()
+        .map(|value| value.unwrap())
+        .collect();
+}
+
+fn text(values: &[&str]) -> Vec<Value> {
+    return values.iter().map(|v| Value::Text(v.to_string())).collect();
+}
+
+fn declared_type(conn: &rusqlite::Connection, column: &str) -> String {
+    return conn
+        .query_row(
+            "SELECT type FROM pragma_table_info('shift') WHERE name = ?",
+            &[column],
+            |row| row.get(0),
+        )
+        .unwrap();
+}
+
+#[test]
+#[ignore]
+fn test_copying_datetimes_as_text() {
+    let conn = copy("datetime_text", &[]);
+    for column in &["day", "starts_at", "confirmed_at", "break_at", "call_at"] {
+        assert_eq!(declared_type(&conn, column), "TEXT", "{}", column);
+    }
+    assert_eq!(
+        shifts(&conn, "starts_at"),
+        text(&["2020-02-29T12:34:56.500", "1969-12-31T23:59:59"])
+    );
+    assert_eq!(
+        shifts(&conn, "confirmed_at"),
+        vec![
+            Value::Text("2020-02-29T12:34:56.500+00:00".to_owned()),
+            Value::Null
+        ]
+    );
+    assert_eq!(
+        shifts(&conn, "call_at"),
+        text(&["09:15:00-03:30", "24:00:00+00:00"])
+    );
+    // all of it readable by SQLite's date and time functions
+    assert_eq!(
+        shifts(&conn, "date(day) || ' ' || time(starts_at)"),
+        text(&["2020-02-29 12:34:56", "1969-12-31 23:59:59"])
+    );
+    assert_eq!(
+        shifts(&conn, "time(call_at)"),
+        text(&["12:45:00", "24:00:00"])
+    );
+}
+
+#[test]
+#[ignore]
+fn test_copying_datetimes_as_unix_time() {
+    let conn = copy("datetime_unix", &["--datetime", "unix"]);
+    assert_eq!(declared_type(&conn, "starts_at"), "INTEGER");
+    assert_eq!(declared_type(&conn, "break_at"), "TEXT");
+    assert_eq!(
+        shifts(&conn, "starts_at"),
+        vec![Value::Integer(1582979696), Value::Integer(-1)]
+    );
+    assert_eq!(
+        shifts(&conn, "datetime(day, 'unixepoch')"),
+        text(&["2020-02-29 00:00:00", "1969-12-31 00:00:00"])
+    );
+    conn.execute("INSERT INTO shift (id) VALUES (3)", rusqlite::NO_PARAMS)
+        .unwrap();
+    let (day, starts_at): (i64, i64) = conn
+        .query_row(
+            "SELECT day, starts_at FROM shift WHERE id = 3",
+            rusqlite::NO_PARAMS,
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(day, 1582934400);
+    assert!(starts_at > 1582979696); // now()
+
+    let conn = copy("datetime_unix_ms", &["--datetime", "unix-ms"]);
+    assert_eq!(
+        shifts(&conn, "confirmed_at"),
+        vec![Value::Integer(1582979696500), Value::Null]
+    );
+}
+
+#[test]
+#[ignore]
+fn test_copying_datetimes_as_julian_days() {
+    let conn = copy("datetime_julian", &["--datetime", "julian"]);
+    assert_eq!(declared_type(&conn, "day"), "REAL");
+    assert_eq!(
+        shifts(&conn, "day"),
+        vec![Value::Real(2458908.5), Value::Real(2440586.5)]
+    );
+    assert_eq!(
+        shifts(&conn, "datetime(starts_at)"),
+        text(&["2020-02-29 12:34:56", "1969-12-31 23:59:59"])
+    );
+}
+
+#[test]
+#[ignore]
+fn test_keeping_timestamptz_offsets() {
+    let conn = copy(
+        "datetime_offset",
+        &["--timestamptz", "offset", "--time-zone", "Asia/Kolkata"],
+    );
+    assert_eq!(
+        shifts(&conn, "confirmed_at"),
+        vec![
+            Value::Text("2020-02-29T18:04:56.500000+05:30".to_owned()),
+            Value::Null
+        ]
+    );
+    assert_eq!(
+        shifts(&conn, "datetime(confirmed_at)"),
+        vec![Value::Text("2020-02-29 12:34:56".to_owned()), Value::Null]
+    );
+    // TIMESTAMP has no time zone to convert from
+    assert_eq!(
+        shifts(&conn, "starts_at"),
+        text(&["2020-02-29T12:34:56.500", "1969-12-31T23:59:59"])
+    );
+}
+
+/// `expr` for every booking, in id order.
+fn bookings(conn: &rusqlite::Connection, expr: &str) -> Vec<Value> {
+    return conn
+        .prepare(&format!("SELECT {} FROM booking ORDER BY id", expr))
+        .unwrap()
+        .query_map(rusqlite::NO_PARAMS, |row| row.get(0))
+        .unwrap()
+        .map(|value| value.unwrap())
+        .collect();
+}
+
+/// SQL for `day` as `--datetime storage` stores it.
+fn stored_day(storage: &str, day: &str) -> String {
+    return match storage {
+        "unix" => format!("CAST(strftime('%s', '{}') AS INTEGER)", day),
+        "unix-ms" => format!("CAST(strftime('%s', '{}') AS INTEGER) * 1000", day),
+        "julian" => format!("julianday('{}')", day),
+        _ => format!("'{}'", day),
+    };
+}
+
+#[test]
+#[ignore]
+fn test_datetime_checks_and_infinities_in_every_storage() {
+    for storage in &["text", "unix", "unix-ms", "julian"] {
+        let conn = copy(
+            &format!("datetime_checks_{}", storage),
+            &["--datetime", storage],
+        );
+        // postgres' infinities have no equivalent in SQLite
+        assert_eq!(
+            bookings(
+                &conn,
+                "day IS NULL AND ends_at IS NULL AND booked_at IS NULL"
+            ),
+            vec![Value::Integer(0), Value::Integer(1)],
+            "{}",
+            storage
+        );
+        let insert = |day: &str| {
+            return conn.execute(
+                &format!(
+                    "INSERT INTO booking (day) VALUES ({})",
+                    stored_day(storage, day)
+                ),
+                rusqlite::NO_PARAMS,
+            );
+        };
+        assert!(insert("2000-01-02").is_ok(), "{}", storage);
+        let err = insert("1999-12-31").unwrap_err().to_string();
+        assert!(
+            err.contains("CHECK constraint failed"),
+            "{} {}",
+            storage,
+            err
+        );
+    }
+
+    let conn = copy("datetime_checks_offset", &["--timestamptz", "offset"]);
+    assert_eq!(
+        bookings(&conn, "booked_at"),
+        vec![
+            Value::Text("2020-02-01T08:00:00.250000+00:00".to_owned()),
+            Value::Null
+        ]
+    );
+}
+
+#[test]
+#[ignore]
+fn test_datetime_defaults_look_like_copied_rows() {
+    let cases = vec![
+        (vec![], r"^\d{4}-\d\d-\d\dT\d\d:\d\d:\d\d(\.\d{3})?\+00:00$"),
+        (
+            vec!["--timestamptz", "offset"],
+            r"^\d{4}-\d\d-\d\dT\d\d:\d\d:\d\d\.\d{6}\+00:00$",
+        ),
+    ];
+    for (args, pattern) in cases {
+        let conn = copy("datetime_defaults", &args);
+        conn.execute("INSERT INTO booking DEFAULT VALUES", rusqlite::NO_PARAMS)
+            .unwrap();
+        let booked_at: String = conn
+            .query_row(
+                "SELECT booked_at FROM booking ORDER BY id DESC LIMIT 1",
+                rusqlite::NO_PARAMS,
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(
+            regex::Regex::new(pattern).unwrap().is_match(&booked_at),
+            "{:?} {}",
+            args,
+            booked_at
+        );
+    }
+    // TIMESTAMP has no offset
+    let conn = copy("datetime_defaults", &[]);
+    conn.execute("INSERT INTO shift (id) VALUES (3)", rusqlite::NO_PARAMS)
+        .unwrap();
+    let starts_at: String = conn
+        .query_row(
+            "SELECT starts_at FROM shift WHERE id = 3",
+            rusqlite::NO_PARAMS,
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert!(
+        regex::Regex::new(r"^\d{4}-\d\d-\d\dT\d\d:\d\d:\d\d(\.\d{3})?$")
+            .unwrap()
+            .is_match(&starts_at),
+        "{}",
+        starts_at
+    );
+}
@@ -0,0 +1,24 @@
+//! These tests need the `fs_db` docker-compose service; see scripts/test_fixtures.sh
+mod common;
+use common::copy;
+use std::{env, fs, path::Path};
+
+/// Run with `UPDATE_GOLDEN=1` to rewrite the golden file after an intended change.
+#[test]
+#[ignore]
+fn test_fs_db_ddl_matches_golden_file() {
+    let conn = copy("golden", &["--schema-only"]);
+    let statements: Vec<String> = conn
+        .prepare("SELECT sql FROM sqlite_master WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%' ORDER BY rowid")
+        .unwrap()
+        .query_map(rusqlite::NO_PARAMS, |row| row.get(0))
+        .unwrap()
+        .map(|sql| sql.unwrap())
+        .collect();
+    let ddl = format!("{};\n", statements.join(";\n\n"));
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/fs_db.sql");
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&golden, &ddl).unwrap();
+    }
+    assert_eq!(ddl, fs::read_to_string(&golden).unwrap());
+}
@@ -0,0 +1,41 @@
+//! These tests need the `fs_db` docker-compose service; see scripts/test_fixtures.sh
+mod common;
+use common::copy;
+
+/// Insert a visit with nothing but defaults, returning its
+/// `(id, token, seen_at, source)`.
+fn visit(conn: &rusqlite::Connection) -> (i64, String, String, String) {
+    conn.execute("INSERT INTO visit DEFAULT VALUES", rusqlite::NO_PARAMS)
+        .unwrap();
+    return conn
+        .query_row(
+            "SELECT id, token, seen_at, source FROM visit WHERE id = last_insert_rowid()",
+            rusqlite::NO_PARAMS,
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .unwrap();
+}
+
+#[test]
+#[ignore]
+fn test_translated_defaults_fire() {
+    let conn = copy("defaults", &[]);
+    let (id, token, seen_at, source) = visit(&conn);
+    assert_eq!(id, 2);
+    assert_eq!(source, "direct, not now()");
+    // gen_random_uuid(): a fresh version 4 uuid each time, in postgres' format
+    let uuid =
+        regex::Regex::new(r"^[0-9a-f]{8}-[0-9a-f]{4}-4[0-9a-f]{3}-[89ab][0-9a-f]{3}-[0-9a-f]{12}$")
+            .unwrap();
+    assert!(uuid.is_match(&token), "{}", token);
+    assert_ne!(visit(&conn).1, token);
+    // now(): the current time, which SQLite's date functions can read
+    let age: f64 = conn
+        .query_row(
+            "SELECT julianday('now') - julianday(?)",
+            &[&seen_at],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert!((0.0..0.01).contains(&age), "{}", seen_at);
+}
@@ -0,0 +1,26 @@
+-- Table inheritance: a plain `SELECT * FROM _event` also returns the rows of
+-- `_read_event` and `_write_event`, so reading each table that way would copy
+-- every child row twice.
+CREATE TABLE public._event (
+  id SERIAL PRIMARY KEY
+  , file_id INTEGER REFERENCES _file(id)
+  , at TIMESTAMP NOT NULL DEFAULT '2021-01-01'
+);
+CREATE TABLE public._read_event (bytes_read INTEGER) INHERITS (public._event);
+CREATE TABLE public._write_event (bytes_written INTEGER) INHERITS (public._event);
+
+INSERT INTO public._event (file_id) SELECT id FROM _file WHERE id % 100 = 0;
+INSERT INTO public._read_event (file_id, bytes_read)
+  SELECT id, id % 4096 FROM _file WHERE id % 100 = 1;
+INSERT INTO public._write_event (file_id, bytes_written)
+  SELECT id, id % 512 FROM _file WHERE id % 100 = 2;
+
+-- A foreign table, served by this same database over postgres_fdw.
+CREATE EXTENSION postgres_fdw;
+CREATE SERVER loopback FOREIGN DATA WRAPPER postgres_fdw OPTIONS (dbname 'postgres');
+CREATE USER MAPPING FOR postgres SERVER loopback;
+CREATE SCHEMA remote;
+CREATE TABLE remote.note (id INTEGER PRIMARY KEY, body TEXT NOT NULL);
+INSERT INTO remote.note VALUES (1, 'first'), (2, 'second');
+CREATE FOREIGN TABLE public._note (id INTEGER, body TEXT)
+  SERVER loopback OPTIONS (schema_name 'remote', table_name 'note');
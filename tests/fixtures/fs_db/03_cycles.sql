@@ -0,0 +1,19 @@
+-- Foreign keys that form cycles, which no table order can satisfy: a folder
+-- tree that references itself, and users and teams that reference each other.
+CREATE TABLE public._folder (
+  id SERIAL PRIMARY KEY
+  , parent_id INTEGER REFERENCES public._folder(id)
+  , name TEXT NOT NULL
+);
+INSERT INTO public._folder (id, parent_id, name) VALUES
+  (1, NULL, 'root'), (2, 1, 'home'), (3, 2, 'me'), (4, 1, 'tmp');
+-- a child inserted before its parent, so rowid order isn't parent-first
+INSERT INTO public._folder (id, parent_id, name) VALUES (6, 5, 'src'), (5, 3, 'code');
+
+CREATE TABLE public._user (id INTEGER PRIMARY KEY, team_id INTEGER NOT NULL);
+CREATE TABLE public._team (id INTEGER PRIMARY KEY, lead_id INTEGER NOT NULL REFERENCES public._user(id));
+BEGIN;
+INSERT INTO public._user VALUES (1, 10), (2, 10), (3, 20);
+INSERT INTO public._team VALUES (10, 1), (20, 3);
+ALTER TABLE public._user ADD FOREIGN KEY (team_id) REFERENCES public._team(id);
+COMMIT;
@@ -0,0 +1,37 @@
+-- Composite keys whose columns aren't in the table's column order, and
+-- constraints that share names across schemas. Each key must keep its own
+-- columns, in its own order.
+CREATE TABLE public._release (
+  major INTEGER
+  , minor INTEGER
+  , tarball_id INTEGER REFERENCES public._file(id)
+  , PRIMARY KEY (minor, major)
+  , CONSTRAINT _release_tarball_key UNIQUE (tarball_id, major)
+);
+CREATE TABLE public._download (
+  id SERIAL PRIMARY KEY
+  , release_major INTEGER NOT NULL
+  , release_minor INTEGER NOT NULL
+  , CONSTRAINT _download_release_fkey FOREIGN KEY (release_minor, release_major)
+    REFERENCES public._release (minor, major)
+);
+INSERT INTO public._release
+  SELECT v.major, v.minor, f.id
+  FROM (VALUES (1, 0, 1), (1, 1, 2), (2, 0, 3)) AS v(major, minor, n)
+    JOIN (SELECT id, row_number() OVER (ORDER BY id) AS n FROM public._file) AS f USING (n);
+INSERT INTO public._download (release_major, release_minor) VALUES (1, 0), (1, 1), (1, 1), (2, 0);
+
+CREATE SCHEMA archive;
+CREATE TABLE archive._release (
+  major INTEGER PRIMARY KEY
+  , tarball_id INTEGER REFERENCES public._file(id)
+  , CONSTRAINT _release_tarball_key UNIQUE (tarball_id)
+);
+CREATE TABLE archive._download (
+  id SERIAL PRIMARY KEY
+  , release_major INTEGER NOT NULL
+  , CONSTRAINT _download_release_fkey FOREIGN KEY (release_major)
+    REFERENCES archive._release (major)
+);
+INSERT INTO archive._release SELECT 0, id FROM public._file ORDER BY id OFFSET 3 LIMIT 1;
+INSERT INTO archive._download (release_major) VALUES (0), (0);
@@ -0,0 +1,12 @@
+-- Foreign keys with referential actions, a MATCH type, and deferral, all of
+-- which SQLite's foreign keys support, too.
+CREATE TABLE public._tag (id SERIAL PRIMARY KEY, name TEXT UNIQUE NOT NULL);
+CREATE TABLE public._file_tag (
+  file_id INTEGER NOT NULL REFERENCES public._file(id) ON DELETE CASCADE ON UPDATE RESTRICT
+  , tag_id INTEGER REFERENCES public._tag(id)
+    MATCH FULL ON DELETE SET NULL ON UPDATE CASCADE DEFERRABLE INITIALLY DEFERRED
+  , tagged_by INTEGER DEFAULT NULL REFERENCES public._user(id) ON DELETE SET DEFAULT DEFERRABLE
+);
+INSERT INTO public._tag (name) VALUES ('photos'), ('music');
+INSERT INTO public._file_tag
+  SELECT f.id, t.id, 1 FROM public._file AS f CROSS JOIN public._tag AS t WHERE f.id % 1000 = 0;
@@ -0,0 +1,15 @@
+-- Names that only work quoted: mixed case, spaces, embedded quotes, and
+-- keywords of postgres, SQLite, or both.
+CREATE TABLE public."Order Items" (
+  "order" SERIAL PRIMARY KEY
+  , "group" TEXT NOT NULL
+  , "Unit ""Price""" INTEGER CHECK ("Unit ""Price""" >= 0)
+  , CONSTRAINT "one group" UNIQUE ("group")
+);
+CREATE TABLE public."select" (
+  id INTEGER PRIMARY KEY
+  , "order" INTEGER REFERENCES public."Order Items" ("order")
+);
+CREATE INDEX "by price" ON public."Order Items" ("Unit ""Price""" DESC, lower("group"));
+INSERT INTO public."Order Items" ("group", "Unit ""Price""") VALUES ('a', 1), ('b', 2);
+INSERT INTO public."select" VALUES (1, 1), (2, NULL);
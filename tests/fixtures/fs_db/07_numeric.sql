@@ -0,0 +1,13 @@
+-- NUMERIC columns, with and without a declared precision and scale, and
+-- values a double couldn't hold exactly.
+CREATE DOMAIN public.money_amount AS NUMERIC(12, 2);
+CREATE TABLE public.invoice (
+  id SERIAL PRIMARY KEY
+  , amount public.money_amount NOT NULL DEFAULT 0
+  , rate NUMERIC(30, 20)
+  , ratio NUMERIC
+);
+INSERT INTO public.invoice (amount, rate, ratio) VALUES
+  (12.5, 0.12345678901234567890, 'NaN')
+  , (-1234567890.99, 1, 0.00000000000000000001)
+  , (0, NULL, 123456789012345678901234567890.5);
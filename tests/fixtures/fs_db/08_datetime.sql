@@ -0,0 +1,13 @@
+-- Dates, timestamps, and times of day, including some before 1970 and
+-- postgres' 24:00:00.
+CREATE TABLE public.shift (
+  id SERIAL PRIMARY KEY
+  , day DATE NOT NULL DEFAULT '2020-02-29'
+  , starts_at TIMESTAMP NOT NULL DEFAULT now()
+  , confirmed_at TIMESTAMPTZ
+  , break_at TIME
+  , call_at TIMETZ
+);
+INSERT INTO public.shift (day, starts_at, confirmed_at, break_at, call_at) VALUES
+  ('2020-02-29', '2020-02-29 12:34:56.5', '2020-02-29 12:34:56.5+00', '12:30', '09:15:00-03:30')
+  , ('1969-12-31', '1969-12-31 23:59:59', NULL, NULL, '24:00:00+00');
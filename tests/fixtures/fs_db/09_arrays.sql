@@ -0,0 +1,25 @@
+-- Arrays of one and two dimensions, with NULL elements, NULL arrays, and an
+-- empty default; and one on a table without a primary key.
+CREATE TABLE public.survey (
+  id SERIAL PRIMARY KEY
+  , tags TEXT[] NOT NULL DEFAULT '{}'
+  , scores INT4[][]
+  , UNIQUE (id, tags)
+);
+INSERT INTO public.survey (tags, scores) VALUES
+  ('{a,b}', '{{1,2},{3,NULL}}')
+  , ('{}', NULL)
+  , ('{"with, comma",NULL}', '{7}');
+CREATE TABLE public.survey_log (
+  survey_id INT4 REFERENCES public.survey (id)
+  , answers TEXT[]
+);
+INSERT INTO public.survey_log VALUES (1, '{yes,no}');
+-- a parent with its own columns named like a child table's
+CREATE TABLE public.sample (
+  id SERIAL PRIMARY KEY
+  , value TEXT
+  , ordinal INT4
+  , readings FLOAT8[]
+);
+INSERT INTO public.sample (value, ordinal, readings) VALUES ('first', 7, '{0.5,1.5}');
@@ -0,0 +1,17 @@
+-- Views over tables in two schemas, which must follow the tables they use
+-- when --schema-naming renames them. Relations without an alias qualify
+-- their columns with their own names.
+CREATE VIEW public._release_downloads AS
+  SELECT _release.major, _release.minor, count(d.id) AS n_downloads
+  FROM public._release
+    LEFT JOIN public._download AS d
+      ON d.release_major = _release.major AND d.release_minor = _release.minor
+  GROUP BY _release.major, _release.minor;
+CREATE VIEW archive._release_downloads AS
+  SELECT _release.major, count(_download.id) AS n_downloads
+  FROM archive._release JOIN archive._download ON _download.release_major = _release.major
+  GROUP BY _release.major;
+-- spans both schemas, which attached databases can't
+CREATE VIEW public._archived_release AS
+  SELECT r.major, r.minor, a.major IS NOT NULL AS archived
+  FROM public._release AS r LEFT JOIN archive._release AS a ON a.major = r.major;
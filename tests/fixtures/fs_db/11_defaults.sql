@@ -0,0 +1,11 @@
+-- Defaults that SQLite spells differently: the current time, and a random
+-- uuid (built in since postgres 13; kept as TEXT, since UUID columns aren't
+-- copied); and a literal that only looks like a call.
+CREATE TABLE public.visit (
+  id SERIAL PRIMARY KEY
+  , token TEXT NOT NULL DEFAULT gen_random_uuid()
+  , seen_at TIMESTAMP NOT NULL DEFAULT now()
+  , source TEXT NOT NULL DEFAULT 'direct, not now()'
+);
+INSERT INTO public.visit (token, seen_at, source) VALUES
+  ('a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11', '2020-02-29 12:34:56', 'search');
@@ -0,0 +1,16 @@
+-- Sequence-backed primary keys: one whose sequence has run ahead of the ids
+-- in its table, as after deletes and rolled-back inserts, and one with an id
+-- inserted past its sequence.
+CREATE TABLE public.ticket (
+  id SERIAL PRIMARY KEY
+  , subject TEXT NOT NULL
+);
+INSERT INTO public.ticket (subject) VALUES ('printer'), ('login');
+SELECT setval('public.ticket_id_seq', 100);
+CREATE TABLE public.ticket_note (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY
+  , ticket_id INTEGER NOT NULL REFERENCES public.ticket(id)
+  , body TEXT NOT NULL
+);
+INSERT INTO public.ticket_note (ticket_id, body) VALUES (1, 'rebooted'), (2, 'reset');
+INSERT INTO public.ticket_note (id, ticket_id, body) VALUES (50, 2, 'imported');
@@ -0,0 +1,9 @@
+-- A populated materialized view, and one that hasn't been populated yet.
+CREATE MATERIALIZED VIEW public.ticket_summary AS
+  SELECT t.id, t.subject, count(n.id) AS notes
+  FROM public.ticket AS t
+    LEFT JOIN public.ticket_note AS n ON n.ticket_id = t.id
+  GROUP BY t.id, t.subject;
+CREATE MATERIALIZED VIEW public.ticket_backlog AS
+  SELECT id, subject FROM public.ticket
+  WITH NO DATA;
@@ -0,0 +1,34 @@
+-- A partitioned table with a partition that is itself partitioned, an index,
+-- and foreign keys both from it and to it.
+CREATE TABLE public.sensor (
+  id SERIAL PRIMARY KEY
+  , name TEXT NOT NULL
+);
+CREATE TABLE public.reading (
+  sensor_id INTEGER NOT NULL REFERENCES public.sensor(id)
+  , taken_on DATE NOT NULL
+  , value REAL NOT NULL
+  , PRIMARY KEY (sensor_id, taken_on)
+) PARTITION BY RANGE (taken_on);
+CREATE INDEX reading_value_idx ON public.reading (value);
+CREATE TABLE public.reading_2020 PARTITION OF public.reading
+  FOR VALUES FROM ('2020-01-01') TO ('2021-01-01')
+  PARTITION BY RANGE (taken_on);
+CREATE TABLE public.reading_2020_h1 PARTITION OF public.reading_2020
+  FOR VALUES FROM ('2020-01-01') TO ('2020-07-01');
+CREATE TABLE public.reading_2020_h2 PARTITION OF public.reading_2020
+  FOR VALUES FROM ('2020-07-01') TO ('2021-01-01');
+CREATE TABLE public.reading_2021 PARTITION OF public.reading
+  FOR VALUES FROM ('2021-01-01') TO ('2022-01-01');
+CREATE TABLE public.reading_flag (
+  sensor_id INTEGER NOT NULL
+  , taken_on DATE NOT NULL
+  , reason TEXT NOT NULL
+  , FOREIGN KEY (sensor_id, taken_on) REFERENCES public.reading (sensor_id, taken_on)
+);
+INSERT INTO public.sensor (name) VALUES ('roof'), ('cellar');
+INSERT INTO public.reading (sensor_id, taken_on, value) VALUES
+  (1, '2020-02-29', 12.5)
+  , (2, '2020-08-01', 18)
+  , (1, '2021-03-01', 9.5);
+INSERT INTO public.reading_flag (sensor_id, taken_on, reason) VALUES (2, '2020-08-01', 'spike');
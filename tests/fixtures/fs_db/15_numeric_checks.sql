@@ -0,0 +1,13 @@
+-- NUMERIC columns under a CHECK constraint and a partial index, which have
+-- to compare the same however the columns are stored, and a NaN in a column
+-- with a declared scale.
+CREATE TABLE public.refund (
+  id SERIAL PRIMARY KEY
+  , amount NUMERIC(8, 2) NOT NULL CHECK (amount > 0 AND amount < 100)
+  , fee NUMERIC(6, 3)
+);
+CREATE INDEX refund_large_idx ON public.refund (amount) WHERE amount >= 50.5;
+INSERT INTO public.refund (amount, fee) VALUES
+  (12.5, 0.125)
+  , (99.99, 'NaN')
+  , (50.5, NULL);
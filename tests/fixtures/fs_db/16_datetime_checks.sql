@@ -0,0 +1,12 @@
+-- Dates and timestamps under CHECK constraints, which have to compare the
+-- same however the columns are stored, postgres' infinities, and a default
+-- that should come out like the copied rows.
+CREATE TABLE public.booking (
+  id SERIAL PRIMARY KEY
+  , day DATE CHECK (day > '2000-01-01')
+  , ends_at TIMESTAMP CHECK (ends_at < '2100-01-01 00:00')
+  , booked_at TIMESTAMPTZ DEFAULT now() CHECK (booked_at > '2000-01-01 00:00+00')
+);
+INSERT INTO public.booking (day, ends_at, booked_at) VALUES
+  ('2020-02-29', '2020-03-01 09:00', '2020-02-01 08:00:00.25+00')
+  , ('infinity', '-infinity', 'infinity');
@@ -0,0 +1,7 @@
+#!/bin/sh
+# only accept TLS connections from outside the container
+cat > "$PGDATA/pg_hba.conf" <<HBA
+local all all trust
+hostssl all all all md5
+HBA
+psql -U postgres -c 'CREATE TABLE tls_check (id SERIAL PRIMARY KEY, note TEXT);'
@@ -0,0 +1,230 @@
+CREATE TABLE "Order Items" (
+  "order" INTEGER PRIMARY KEY NOT NULL -- INT4
+  , "group" TEXT NOT NULL -- TEXT
+  , "Unit ""Price""" INTEGER -- INT4
+  , CONSTRAINT "one group" UNIQUE ("group")
+  , CONSTRAINT "Order Items_Unit ""Price""_check" CHECK (("Unit ""Price""" >= 0))
+);
+
+CREATE TABLE _blob (
+  id INTEGER PRIMARY KEY NOT NULL -- INT8
+  , n_bytes INTEGER -- INT4
+  , sha256 TEXT NOT NULL -- BPCHAR
+  , CONSTRAINT _blob_sha256_key UNIQUE (sha256)
+);
+
+CREATE TABLE _directory (
+  id INTEGER PRIMARY KEY NOT NULL -- INT4
+  , absolute_path TEXT NOT NULL -- TEXT
+  , CONSTRAINT _directory_absolute_path_key UNIQUE (absolute_path)
+);
+
+CREATE TABLE _file_name (
+  id INTEGER PRIMARY KEY NOT NULL -- INT4
+  , name TEXT -- TEXT
+  , CONSTRAINT _file_name_name_key UNIQUE (name)
+);
+
+CREATE TABLE _file (
+  id INTEGER PRIMARY KEY NOT NULL -- INT4
+  , directory_id INTEGER -- INT4
+  , file_name_id INTEGER -- INT4
+  , mode BLOB -- BIT
+  , blob_id INTEGER -- INT4
+  , CONSTRAINT unique_file_info UNIQUE (directory_id, file_name_id, mode, blob_id)
+  , CONSTRAINT _file_blob_id_fkey FOREIGN KEY (blob_id) REFERENCES _blob(id)
+  , CONSTRAINT _file_directory_id_fkey FOREIGN KEY (directory_id) REFERENCES _directory(id)
+  , CONSTRAINT _file_file_name_id_fkey FOREIGN KEY (file_name_id) REFERENCES _file_name(id)
+);
+
+CREATE TABLE _release (
+  major INTEGER NOT NULL -- INT4
+  , minor INTEGER NOT NULL -- INT4
+  , tarball_id INTEGER -- INT4
+  , CONSTRAINT _release_pkey PRIMARY KEY (minor, major)
+  , CONSTRAINT _release_tarball_key UNIQUE (tarball_id, major)
+  , CONSTRAINT _release_tarball_id_fkey FOREIGN KEY (tarball_id) REFERENCES _file(id)
+);
+
+CREATE TABLE _download (
+  id INTEGER PRIMARY KEY NOT NULL -- INT4
+  , release_major INTEGER NOT NULL -- INT4
+  , release_minor INTEGER NOT NULL -- INT4
+  , CONSTRAINT _download_release_fkey FOREIGN KEY (release_minor, release_major) REFERENCES _release(minor, major)
+);
+
+CREATE TABLE _event (
+  id INTEGER PRIMARY KEY NOT NULL -- INT4
+  , file_id INTEGER -- INT4
+  , at TEXT NOT NULL DEFAULT '2021-01-01T00:00:00' -- TIMESTAMP
+  , CONSTRAINT _event_file_id_fkey FOREIGN KEY (file_id) REFERENCES _file(id)
+);
+
+CREATE TABLE _team (
+  id INTEGER NOT NULL -- INT4
+  , lead_id INTEGER NOT NULL -- INT4
+  , CONSTRAINT _team_pkey PRIMARY KEY (id)
+  , CONSTRAINT _team_lead_id_fkey FOREIGN KEY (lead_id) REFERENCES _user(id)
+);
+
+CREATE TABLE _user (
+  id INTEGER NOT NULL -- INT4
+  , team_id INTEGER NOT NULL -- INT4
+  , CONSTRAINT _user_pkey PRIMARY KEY (id)
+  , CONSTRAINT _user_team_id_fkey FOREIGN KEY (team_id) REFERENCES _team(id)
+);
+
+CREATE TABLE _tag (
+  id INTEGER PRIMARY KEY NOT NULL -- INT4
+  , name TEXT NOT NULL -- TEXT
+  , CONSTRAINT _tag_name_key UNIQUE (name)
+);
+
+CREATE TABLE _file_tag (
+  file_id INTEGER NOT NULL -- INT4
+  , tag_id INTEGER -- INT4
+  , tagged_by INTEGER -- INT4
+  , CONSTRAINT _file_tag_file_id_fkey FOREIGN KEY (file_id) REFERENCES _file(id) ON DELETE CASCADE ON UPDATE RESTRICT
+  , CONSTRAINT _file_tag_tag_id_fkey FOREIGN KEY (tag_id) REFERENCES _tag(id) MATCH FULL ON DELETE SET NULL ON UPDATE CASCADE DEFERRABLE INITIALLY DEFERRED
+  , CONSTRAINT _file_tag_tagged_by_fkey FOREIGN KEY (tagged_by) REFERENCES _user(id) ON DELETE SET DEFAULT DEFERRABLE INITIALLY IMMEDIATE
+);
+
+CREATE TABLE _folder (
+  id INTEGER PRIMARY KEY NOT NULL -- INT4
+  , parent_id INTEGER -- INT4
+  , name TEXT NOT NULL -- TEXT
+  , CONSTRAINT _folder_parent_id_fkey FOREIGN KEY (parent_id) REFERENCES _folder(id)
+);
+
+CREATE TABLE _read_event (
+  id INTEGER NOT NULL -- INT4 DEFAULT nextval('_event_id_seq'::regclass)
+  , file_id INTEGER -- INT4
+  , at TEXT NOT NULL DEFAULT '2021-01-01T00:00:00' -- TIMESTAMP
+  , bytes_read INTEGER -- INT4
+);
+
+CREATE TABLE _write_event (
+  id INTEGER NOT NULL -- INT4 DEFAULT nextval('_event_id_seq'::regclass)
+  , file_id INTEGER -- INT4
+  , at TEXT NOT NULL DEFAULT '2021-01-01T00:00:00' -- TIMESTAMP
+  , bytes_written INTEGER -- INT4
+);
+
+CREATE TABLE booking (
+  id INTEGER PRIMARY KEY NOT NULL -- INT4
+  , day TEXT -- DATE
+  , ends_at TEXT -- TIMESTAMP
+  , booked_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%S', CURRENT_TIMESTAMP) || replace(substr(strftime('%f', CURRENT_TIMESTAMP), 3), '.000', '') || '+00:00') -- TIMESTAMPTZ
+  , CONSTRAINT booking_booked_at_check CHECK ((julianday(booked_at) > julianday('2000-01-01 00:00:00+00:00')))
+  , CONSTRAINT booking_day_check CHECK ((julianday(day) > julianday('2000-01-01')))
+  , CONSTRAINT booking_ends_at_check CHECK ((julianday(ends_at) < julianday('2100-01-01 00:00:00')))
+);
+
+CREATE TABLE invoice (
+  id INTEGER PRIMARY KEY NOT NULL -- INT4
+  , amount TEXT NOT NULL DEFAULT '0.00' -- NUMERIC(12,2)
+  , rate TEXT -- NUMERIC(30,20)
+  , ratio TEXT -- NUMERIC
+);
+
+CREATE TABLE sensor (
+  id INTEGER PRIMARY KEY NOT NULL -- INT4
+  , name TEXT NOT NULL -- TEXT
+);
+
+CREATE TABLE reading (
+  sensor_id INTEGER NOT NULL -- INT4
+  , taken_on TEXT NOT NULL -- DATE
+  , value REAL NOT NULL -- FLOAT4
+  , CONSTRAINT reading_pkey PRIMARY KEY (sensor_id, taken_on)
+  , CONSTRAINT reading_sensor_id_fkey FOREIGN KEY (sensor_id) REFERENCES sensor(id)
+);
+
+CREATE TABLE reading_flag (
+  sensor_id INTEGER NOT NULL -- INT4
+  , taken_on TEXT NOT NULL -- DATE
+  , reason TEXT NOT NULL -- TEXT
+  , CONSTRAINT reading_flag_sensor_id_taken_on_fkey FOREIGN KEY (sensor_id, taken_on) REFERENCES reading(sensor_id, taken_on)
+);
+
+CREATE TABLE refund (
+  id INTEGER PRIMARY KEY NOT NULL -- INT4
+  , amount TEXT NOT NULL -- NUMERIC(8,2)
+  , fee TEXT -- NUMERIC(6,3)
+  , CONSTRAINT refund_amount_check CHECK (((CAST(amount AS REAL) > (0)) AND (CAST(amount AS REAL) < (100))))
+);
+
+CREATE TABLE sample (
+  id INTEGER PRIMARY KEY NOT NULL -- INT4
+  , value TEXT -- TEXT
+  , ordinal INTEGER -- INT4
+  , readings TEXT -- FLOAT8[] as JSON
+);
+
+CREATE TABLE "select" (
+  id INTEGER NOT NULL -- INT4
+  , "order" INTEGER -- INT4
+  , CONSTRAINT select_pkey PRIMARY KEY (id)
+  , CONSTRAINT select_order_fkey FOREIGN KEY ("order") REFERENCES "Order Items"("order")
+);
+
+CREATE TABLE shift (
+  id INTEGER PRIMARY KEY NOT NULL -- INT4
+  , day TEXT NOT NULL DEFAULT '2020-02-29' -- DATE
+  , starts_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%S', CURRENT_TIMESTAMP) || replace(substr(strftime('%f', CURRENT_TIMESTAMP), 3), '.000', '')) -- TIMESTAMP
+  , confirmed_at TEXT -- TIMESTAMPTZ
+  , break_at TEXT -- TIME
+  , call_at TEXT -- TIMETZ
+);
+
+CREATE TABLE survey (
+  id INTEGER PRIMARY KEY NOT NULL -- INT4
+  , tags TEXT NOT NULL DEFAULT '[]' -- TEXT[] as JSON
+  , scores TEXT -- INT4[] as JSON
+  , CONSTRAINT survey_id_tags_key UNIQUE (id, tags)
+);
+
+CREATE TABLE survey_log (
+  survey_id INTEGER -- INT4
+  , answers TEXT -- TEXT[] as JSON
+  , CONSTRAINT survey_log_survey_id_fkey FOREIGN KEY (survey_id) REFERENCES survey(id)
+);
+
+CREATE TABLE ticket (
+  id INTEGER PRIMARY KEY NOT NULL -- INT4
+  , subject TEXT NOT NULL -- TEXT
+);
+
+CREATE TABLE ticket_note (
+  id INTEGER PRIMARY KEY NOT NULL -- INT8
+  , ticket_id INTEGER NOT NULL -- INT4
+  , body TEXT NOT NULL -- TEXT
+  , CONSTRAINT ticket_note_ticket_id_fkey FOREIGN KEY (ticket_id) REFERENCES ticket(id)
+);
+
+CREATE TABLE ticket_summary (
+  id INTEGER -- INT4
+  , subject TEXT -- TEXT
+  , notes INTEGER -- INT8
+);
+
+CREATE TABLE visit (
+  id INTEGER PRIMARY KEY NOT NULL -- INT4
+  , token TEXT NOT NULL DEFAULT (lower(hex(randomblob(4))) || '-' || lower(hex(randomblob(2))) || '-4' || substr(lower(hex(randomblob(2))), 2) || '-' || substr('89ab', 1 + (abs(random()) % 4), 1) || substr(lower(hex(randomblob(2))), 2) || '-' || lower(hex(randomblob(6)))) -- TEXT
+  , seen_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%S', CURRENT_TIMESTAMP) || replace(substr(strftime('%f', CURRENT_TIMESTAMP), 3), '.000', '')) -- TIMESTAMP
+  , source TEXT NOT NULL DEFAULT 'direct, not now()' -- TEXT
+);
+
+CREATE VIEW _release_downloads AS
+ SELECT _release.major,
+    _release.minor,
+    count(d.id) AS n_downloads
+   FROM (_release
+     LEFT JOIN _download d ON (((d.release_major = _release.major) AND (d.release_minor = _release.minor))))
+  GROUP BY _release.major, _release.minor;
+
+CREATE INDEX "by price" ON "Order Items" ("Unit ""Price""" DESC, lower("group"));
+
+CREATE INDEX reading_value_idx ON reading (value);
+
+CREATE INDEX refund_large_idx ON refund (amount) WHERE (CAST(amount AS REAL) >= 50.5);
@@ -0,0 +1,30 @@
+//! These tests need the `fs_db` docker-compose service; see scripts/test_fixtures.sh
+mod common;
+use common::{copy, count};
+
+#[test]
+#[ignore]
+fn test_parents_hold_only_their_own_rows() {
+    let conn = copy("only", &[]);
+    assert_eq!(count(&conn, "_event"), Some(95));
+    assert_eq!(count(&conn, "_read_event"), Some(99));
+    assert_eq!(count(&conn, "_write_event"), Some(98));
+}
+
+#[test]
+#[ignore]
+fn test_flattening_inheritance() {
+    let conn = copy("flatten", &["--inheritance", "flatten"]);
+    assert_eq!(count(&conn, "_event"), Some(292));
+    assert_eq!(count(&conn, "_read_event"), None);
+    assert_eq!(count(&conn, "_write_event"), None);
+}
+
+#[test]
+#[ignore]
+fn test_foreign_tables() {
+    let skipped = copy("skip_foreign", &[]);
+    assert_eq!(count(&skipped, "_note"), None);
+    let copied = copy("copy_foreign", &["--foreign-tables", "copy"]);
+    assert_eq!(count(&copied, "_note"), Some(2));
+}
@@ -0,0 +1,72 @@
+//! These tests need the `fs_db` docker-compose service; see scripts/test_fixtures.sh
+mod common;
+use common::copy;
+
+/// The type of `name` in sqlite_master, if it was created.
+fn kind(conn: &rusqlite::Connection, name: &str) -> Option<String> {
+    return conn
+        .query_row(
+            "SELECT type FROM sqlite_master WHERE name = ?",
+            &[name],
+            |row| row.get(0),
+        )
+        .ok();
+}
+
+/// Every `(id, subject, ...)` row of `sql`, as text.
+fn tickets(conn: &rusqlite::Connection, sql: &str) -> Vec<String> {
+    return conn
+        .prepare(sql)
+        .unwrap()
+        .query_map(rusqlite::NO_PARAMS, |row| {
+            let id: i64 = row.get(0)?;
+            let subject: String = row.get(1)?;
+            return Ok(format!("{} {}", id, subject));
+        })
+        .unwrap()
+        .map(|row| row.unwrap())
+        .collect();
+}
+
+#[test]
+#[ignore]
+fn test_materialized_views_as_tables() {
+    let conn = copy("matviews_table", &[]);
+    assert_eq!(kind(&conn, "ticket_summary"), Some("table".to_owned()));
+    let notes: Vec<i64> = conn
+        .prepare("SELECT notes FROM ticket_summary ORDER BY id")
+        .unwrap()
+        .query_map(rusqlite::NO_PARAMS, |row| row.get(0))
+        .unwrap()
+        .map(|n| n.unwrap())
+        .collect();
+    assert_eq!(notes, vec![1, 2]);
+    // it has no rows to copy
+    assert_eq!(kind(&conn, "ticket_backlog"), None);
+}
+
+#[test]
+#[ignore]
+fn test_materialized_views_as_views() {
+    let conn = copy("matviews_view", &["--materialized-views", "view"]);
+    assert_eq!(kind(&conn, "ticket_summary"), Some("view".to_owned()));
+    assert_eq!(kind(&conn, "ticket_backlog"), Some("view".to_owned()));
+    // views read the copied tables, so even the unpopulated one has rows
+    assert_eq!(
+        tickets(&conn, "SELECT * FROM ticket_backlog ORDER BY id"),
+        vec!["1 printer", "2 login"]
+    );
+    conn.execute(
+        "INSERT INTO ticket_note (ticket_id, body) VALUES (1, 'again')",
+        rusqlite::NO_PARAMS,
+    )
+    .unwrap();
+    let notes: i64 = conn
+        .query_row(
+            "SELECT notes FROM ticket_summary WHERE id = 1",
+            rusqlite::NO_PARAMS,
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(notes, 2);
+}
@@ -0,0 +1,203 @@
+//! These tests need the `fs_db` docker-compose service; see scripts/test_fixtures.sh
+mod common;
+use common::copy;
+use rusqlite::types::Value;
+
+/// `column` of every invoice, in id order.
+fn invoice_column(conn: &rusqlite::Connection, column: &str) -> Vec<Value> {
+    return conn
+        .prepare(&format!("SELECT {} FROM invoice ORDER BY id", column))
+        .unwrap()
+        .query_map(rusqlite::NO_PARAMS, |row| row.get(0))
+        .unwrap()
+        .map(|value| value.unwrap())
+        .collect();
+}
+
+fn text(values: &[&str]) -> Vec<Value> {
+    return values.iter().map(|v| Value::Text(v.to_string())).collect();
+}
+
+/// The declared type and default of `column`.
+fn declared(conn: &rusqlite::Connection, column: &str) -> (String, Option<String>) {
+    return conn
+        .query_row(
+            "SELECT type, dflt_value FROM pragma_table_info('invoice') WHERE name = ?",
+            &[column],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+}
+
+#[test]
+#[ignore]
+fn test_copying_numerics_as_text() {
+    let conn = copy("numeric_text", &[]);
+    assert_eq!(
+        invoice_column(&conn, "amount"),
+        text(&["12.50", "-1234567890.99", "0.00"])
+    );
+    assert_eq!(
+        invoice_column(&conn, "rate"),
+        vec![
+            Value::Text("0.12345678901234567890".to_owned()),
+            Value::Text("1.00000000000000000000".to_owned()),
+            Value::Null,
+        ]
+    );
+    assert_eq!(
+        invoice_column(&conn, "ratio"),
+        vec![
+            Value::Null, // NaN
+            Value::Text("0.00000000000000000001".to_owned()),
+            Value::Text("123456789012345678901234567890.5".to_owned()),
+        ]
+    );
+    assert_eq!(
+        declared(&conn, "amount"),
+        ("TEXT".to_owned(), Some("'0.00'".to_owned()))
+    );
+}
+
+#[test]
+#[ignore]
+fn test_copying_numerics_as_scaled_integers() {
+    let conn = copy("numeric_integer", &["--numeric", "integer"]);
+    assert_eq!(
+        invoice_column(&conn, "amount"),
+        vec![
+            Value::Integer(1250),
+            Value::Integer(-123456789099),
+            Value::Integer(0)
+        ]
+    );
+    assert_eq!(
+        declared(&conn, "amount"),
+        ("INTEGER".to_owned(), Some("0".to_owned()))
+    );
+    // too many digits, and no scale at all: both fall back to text
+    assert_eq!(declared(&conn, "rate").0, "TEXT");
+    assert_eq!(declared(&conn, "ratio").0, "TEXT");
+    assert_eq!(
+        invoice_column(&conn, "ratio"),
+        vec![
+            Value::Null, // NaN
+            Value::Text("0.00000000000000000001".to_owned()),
+            Value::Text("123456789012345678901234567890.5".to_owned()),
+        ]
+    );
+}
+
+#[test]
+#[ignore]
+fn test_overriding_numeric_storage_per_column() {
+    let conn = copy(
+        "numeric_columns",
+        &[
+            "--numeric",
+            "blob",
+            "--numeric-column",
+            "invoice.amount=integer",
+            "--numeric-column",
+            "public.invoice.amount=real",
+        ],
+    );
+    // the last override that names a column wins
+    assert_eq!(declared(&conn, "amount").0, "REAL");
+    assert_eq!(
+        invoice_column(&conn, "amount"),
+        vec![
+            Value::Real(12.5),
+            Value::Real(-1234567890.99),
+            Value::Real(0.0)
+        ]
+    );
+    assert_eq!(declared(&conn, "ratio").0, "BLOB");
+    let types: Vec<Value> = invoice_column(&conn, "typeof(ratio)");
+    assert_eq!(types, text(&["null", "blob", "blob"])); // NaN is NULL
+}
+
+/// Try inserting a refund of `amount`, as it's stored, returning SQLite's
+/// error if it's refused.
+fn refund(conn: &rusqlite::Connection, amount: &dyn rusqlite::ToSql) -> Result<(), String> {
+    return conn
+        .execute("INSERT INTO refund (amount) VALUES (?)", &[amount])
+        .map(|_| ())
+        .map_err(|e| e.to_string());
+}
+
+/// The rows the partial index on refunds of at least 50.50 holds, read
+/// through it with its own predicate.
+fn large_refunds(conn: &rusqlite::Connection) -> Option<i64> {
+    let index: String = conn
+        .query_row(
+            "SELECT sql FROM sqlite_master WHERE name = 'refund_large_idx'",
+            rusqlite::NO_PARAMS,
+            |row| row.get(0),
+        )
+        .ok()?;
+    let predicate = &index[index.find(" WHERE ")?..];
+    let sql = format!(
+        "SELECT count(*) FROM refund INDEXED BY refund_large_idx{}",
+        predicate
+    );
+    return Some(
+        conn.query_row(&sql, rusqlite::NO_PARAMS, |row| row.get(0))
+            .unwrap(),
+    );
+}
+
+#[test]
+#[ignore]
+fn test_numeric_checks_and_nan_in_every_storage() {
+    let cases = vec![
+        (
+            "text",
+            Value::Text("99.50".to_owned()),
+            Value::Text("100.00".to_owned()),
+        ),
+        ("real", Value::Real(99.5), Value::Real(100.0)),
+        ("integer", Value::Integer(9950), Value::Integer(10000)),
+    ];
+    for (storage, ok, too_much) in cases {
+        let conn = copy(
+            &format!("numeric_checks_{}", storage),
+            &["--numeric", storage],
+        );
+        let fees: Vec<Value> = conn
+            .prepare("SELECT fee IS NULL FROM refund ORDER BY id")
+            .unwrap()
+            .query_map(rusqlite::NO_PARAMS, |row| row.get(0))
+            .unwrap()
+            .map(|value| value.unwrap())
+            .collect();
+        // NaN became NULL
+        assert_eq!(
+            fees,
+            vec![Value::Integer(0), Value::Integer(1), Value::Integer(1)]
+        );
+        assert_eq!(refund(&conn, &ok), Ok(()), "{}", storage);
+        assert!(
+            refund(&conn, &too_much)
+                .unwrap_err()
+                .contains("CHECK constraint failed"),
+            "{}",
+            storage
+        );
+        // 99.99, 50.50, and 99.50, but not 12.50
+        assert_eq!(large_refunds(&conn), Some(3), "{}", storage);
+    }
+    // blobs can't be compared, so the constraint is commented out and the
+    // index skipped
+    let conn = copy("numeric_checks_blob", &["--numeric", "blob"]);
+    assert_eq!(refund(&conn, &vec![0u8]), Ok(()));
+    assert_eq!(large_refunds(&conn), None);
+    let fee: Value = conn
+        .query_row(
+            "SELECT fee FROM refund WHERE id = 2",
+            rusqlite::NO_PARAMS,
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(fee, Value::Null);
+}
@@ -0,0 +1,62 @@
+//! These tests need the `fs_db` docker-compose service; see scripts/test_fixtures.sh
+mod common;
+use common::{copy, count};
+
+/// The name and row count of every table, by name.
+fn row_counts(conn: &rusqlite::Connection) -> Vec<(String, Option<i64>)> {
+    let tables: Vec<String> = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+        .unwrap()
+        .query_map(rusqlite::NO_PARAMS, |row| row.get(0))
+        .unwrap()
+        .map(|name| name.unwrap())
+        .collect();
+    return tables
+        .into_iter()
+        .map(|table| {
+            let n = count(conn, &pg_to_sqlite3::quote::sqlite(&table));
+            return (table, n);
+        })
+        .collect();
+}
+
+#[test]
+#[ignore]
+fn test_parallel_copy_matches_serial_copy() {
+    let serial = copy("jobs_1", &["--jobs", "1"]);
+    let parallel = copy("jobs_4", &["--jobs", "4"]);
+    let counts = row_counts(&serial);
+    assert!(counts
+        .iter()
+        .any(|(table, n)| table == "_file" && *n > Some(0)));
+    assert_eq!(row_counts(&parallel), counts);
+    let violations = parallel
+        .prepare("PRAGMA foreign_key_check")
+        .unwrap()
+        .query_map(rusqlite::NO_PARAMS, |row| row.get::<usize, String>(0))
+        .unwrap()
+        .count();
+    assert_eq!(violations, 0);
+    // and the rows themselves, not just how many there are
+    let serial_path = std::env::temp_dir().join("pg_to_sqlite3_jobs_1.db");
+    parallel
+        .execute(
+            "ATTACH DATABASE ? AS serial",
+            &[serial_path.to_str().unwrap()],
+        )
+        .unwrap();
+    for (table, _) in counts {
+        let table = pg_to_sqlite3::quote::sqlite(&table);
+        let differing: i64 = parallel
+            .query_row(
+                &format!(
+                    "SELECT count(*) FROM (SELECT * FROM main.{0} EXCEPT SELECT * FROM serial.{0})",
+                    table
+                ),
+                rusqlite::NO_PARAMS,
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(differing, 0, "{}", table);
+    }
+}
@@ -0,0 +1,104 @@
+//! These tests need the `fs_db` docker-compose service; see scripts/test_fixtures.sh
+mod common;
+use common::{copy, count};
+
+/// The names of `kind`s (`table` or `index`) in sqlite_master on tables named
+/// like `reading%`, other than those SQLite makes for its own constraints.
+fn names(conn: &rusqlite::Connection, kind: &str) -> Vec<String> {
+    return conn
+        .prepare(
+            "SELECT name FROM sqlite_master WHERE type = ? AND tbl_name LIKE 'reading%' \
+            AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )
+        .unwrap()
+        .query_map(&[kind], |row| row.get(0))
+        .unwrap()
+        .map(|name| name.unwrap())
+        .collect();
+}
+
+/// `(from table, to table)` of each foreign key on the `reading%` tables.
+fn fkeys(conn: &rusqlite::Connection) -> Vec<(String, String)> {
+    return conn
+        .prepare(
+            "SELECT m.name, fk.\"table\" FROM sqlite_master AS m, pragma_foreign_key_list(m.name) AS fk \
+            WHERE m.type = 'table' AND m.name LIKE 'reading%' AND fk.seq = 0 ORDER BY 1, 2",
+        )
+        .unwrap()
+        .query_map(rusqlite::NO_PARAMS, |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .map(|fk| fk.unwrap())
+        .collect();
+}
+
+fn pairs(values: &[(&str, &str)]) -> Vec<(String, String)> {
+    return values
+        .iter()
+        .map(|(a, b)| (a.to_string(), b.to_string()))
+        .collect();
+}
+
+#[test]
+#[ignore]
+fn test_merging_partitions() {
+    let conn = copy("partitions_merge", &[]);
+    assert_eq!(names(&conn, "table"), vec!["reading", "reading_flag"]);
+    assert_eq!(count(&conn, "reading"), Some(3));
+    // declared once, on the merged table
+    assert_eq!(names(&conn, "index"), vec!["reading_value_idx"]);
+    assert_eq!(
+        fkeys(&conn),
+        pairs(&[("reading", "sensor"), ("reading_flag", "reading")])
+    );
+}
+
+#[test]
+#[ignore]
+fn test_naming_each_rows_partition() {
+    let conn = copy("partitions_column", &["--partition-column", "part"]);
+    let parts: Vec<String> = conn
+        .prepare("SELECT part FROM reading ORDER BY taken_on")
+        .unwrap()
+        .query_map(rusqlite::NO_PARAMS, |row| row.get(0))
+        .unwrap()
+        .map(|part| part.unwrap())
+        .collect();
+    assert_eq!(
+        parts,
+        vec!["reading_2020_h1", "reading_2020_h2", "reading_2021"]
+    );
+    assert_eq!(names(&conn, "index"), vec!["reading_value_idx"]);
+}
+
+#[test]
+#[ignore]
+fn test_separating_partitions() {
+    let conn = copy("partitions_separate", &["--partitions", "separate"]);
+    assert_eq!(
+        names(&conn, "table"),
+        vec![
+            "reading_2020_h1",
+            "reading_2020_h2",
+            "reading_2021",
+            "reading_flag"
+        ]
+    );
+    assert_eq!(count(&conn, "reading_2020_h2"), Some(1));
+    assert_eq!(
+        names(&conn, "index"),
+        vec![
+            "reading_2020_h1_value_idx",
+            "reading_2020_h2_value_idx",
+            "reading_2021_value_idx"
+        ]
+    );
+    // no one partition holds every reading a flag could reference
+    assert_eq!(
+        fkeys(&conn),
+        pairs(&[
+            ("reading_2020_h1", "sensor"),
+            ("reading_2020_h2", "sensor"),
+            ("reading_2021", "sensor")
+        ])
+    );
+}
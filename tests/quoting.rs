@@ -0,0 +1,50 @@
+//! These tests need the `fs_db` docker-compose service; see scripts/test_fixtures.sh
+mod common;
+use common::{copy, count, src};
+use pg_to_sqlite3::quote;
+use proptest::prelude::*;
+use std::cell::RefCell;
+
+#[test]
+#[ignore]
+fn test_quoting_like_quote_ident() {
+    let client = RefCell::new(postgres::Client::connect(&src(), postgres::NoTls).unwrap());
+    let keywords = client
+        .borrow_mut()
+        .query("SELECT word, quote_ident(word) FROM pg_get_keywords()", &[])
+        .unwrap();
+    for row in keywords {
+        assert_eq!(quote::pg(row.get(0)), row.get::<usize, String>(1));
+    }
+    proptest!(|(ident in "[^\u{0}]{1,20}|[a-z_][a-z0-9_]{0,10}")| {
+        let row = client.borrow_mut().query_one("SELECT quote_ident($1)", &[&ident]).unwrap();
+        prop_assert_eq!(quote::pg(&ident), row.get::<usize, String>(0));
+    });
+}
+
+#[test]
+#[ignore]
+fn test_copying_names_that_need_quotes() {
+    let conn = copy("quoting", &[]);
+    assert_eq!(count(&conn, "\"Order Items\""), Some(2));
+    assert_eq!(count(&conn, "\"select\""), Some(2));
+    let columns: Vec<String> = conn
+        .prepare("SELECT name FROM pragma_table_info('Order Items')")
+        .unwrap()
+        .query_map(rusqlite::NO_PARAMS, |row| row.get(0))
+        .unwrap()
+        .map(|name| name.unwrap())
+        .collect();
+    assert_eq!(columns, vec!["order", "group", "Unit \"Price\""]);
+    let index: String = conn
+        .query_row(
+            "SELECT sql FROM sqlite_master WHERE name = 'by price'",
+            rusqlite::NO_PARAMS,
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(
+        index,
+        "CREATE INDEX \"by price\" ON \"Order Items\" (\"Unit \"\"Price\"\"\" DESC, lower(\"group\"))"
+    );
+}
@@ -0,0 +1,74 @@
+//! These tests need the `fs_db` docker-compose service; see scripts/test_fixtures.sh
+mod common;
+use common::copy;
+
+fn ddl(conn: &rusqlite::Connection, table: &str) -> String {
+    return conn
+        .query_row(
+            "SELECT sql FROM sqlite_master WHERE name = ?",
+            &[table],
+            |row| row.get(0),
+        )
+        .unwrap();
+}
+
+/// The id SQLite gives a new row of `table`.
+fn next_id(conn: &rusqlite::Connection, table: &str, values: &str) -> i64 {
+    conn.execute(
+        &format!("INSERT INTO {} {}", table, values),
+        rusqlite::NO_PARAMS,
+    )
+    .unwrap();
+    return conn.last_insert_rowid();
+}
+
+#[test]
+#[ignore]
+fn test_serial_keys_become_rowid_aliases() {
+    let conn = copy("rowid_aliases", &[]);
+    let ticket = ddl(&conn, "ticket");
+    assert!(
+        ticket.contains("id INTEGER PRIMARY KEY NOT NULL"),
+        "{}",
+        ticket
+    );
+    assert!(!ticket.contains("AUTOINCREMENT"), "{}", ticket);
+    assert!(!ticket.contains("ticket_pkey"), "{}", ticket);
+    // SQLite numbers new rows after the largest id, not the sequence
+    assert_eq!(next_id(&conn, "ticket", "(subject) VALUES ('vpn')"), 3);
+}
+
+#[test]
+#[ignore]
+fn test_autoincrement_continues_the_sequences() {
+    let conn = copy("autoincrement", &["--autoincrement"]);
+    let ticket = ddl(&conn, "ticket");
+    assert!(
+        ticket.contains("id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL"),
+        "{}",
+        ticket
+    );
+    assert!(!ticket.contains("ticket_pkey"), "{}", ticket);
+    let note = ddl(&conn, "ticket_note");
+    assert!(note.contains("PRIMARY KEY AUTOINCREMENT"), "{}", note);
+    let seq = |table: &str| -> i64 {
+        return conn
+            .query_row(
+                "SELECT seq FROM sqlite_sequence WHERE name = ?",
+                &[table],
+                |row| row.get(0),
+            )
+            .unwrap();
+    };
+    assert_eq!(seq("ticket"), 100);
+    assert_eq!(seq("ticket_note"), 50); // its largest id, not its sequence's 2
+    assert_eq!(next_id(&conn, "ticket", "(subject) VALUES ('vpn')"), 101);
+    assert_eq!(
+        next_id(
+            &conn,
+            "ticket_note",
+            "(ticket_id, body) VALUES (1, 'fixed')"
+        ),
+        51
+    );
+}
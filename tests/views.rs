@@ -0,0 +1,124 @@
+//! These tests need the `fs_db` docker-compose service; see scripts/test_fixtures.sh
+mod common;
+use common::{copy, src};
+use std::process::Command;
+
+/// Every row of `sql`, as text.
+fn rows(conn: &rusqlite::Connection, sql: &str) -> Vec<Vec<String>> {
+    let mut statement = conn.prepare(sql).unwrap();
+    let n = statement.column_count();
+    return statement
+        .query_map(rusqlite::NO_PARAMS, |row| {
+            return (0..n)
+                .map(|i| row.get::<usize, i64>(i).map(|v| v.to_string()))
+                .collect();
+        })
+        .unwrap()
+        .map(|row| row.unwrap())
+        .collect();
+}
+
+fn n_views(conn: &rusqlite::Connection, database: &str) -> i64 {
+    return conn
+        .query_row(
+            &format!(
+                "SELECT count(*) FROM {}.sqlite_master WHERE type = 'view'",
+                database
+            ),
+            rusqlite::NO_PARAMS,
+            |row| row.get(0),
+        )
+        .unwrap();
+}
+
+fn strings(values: &[&[&str]]) -> Vec<Vec<String>> {
+    return values
+        .iter()
+        .map(|row| row.iter().map(|v| v.to_string()).collect())
+        .collect();
+}
+
+#[test]
+#[ignore]
+fn test_views_of_one_schema() {
+    let conn = copy("views_bare", &[]);
+    assert_eq!(
+        rows(&conn, "SELECT * FROM _release_downloads ORDER BY 1, 2"),
+        strings(&[&["1", "0", "1"], &["1", "1", "2"], &["2", "0", "1"]])
+    );
+    // it uses archive._release, which isn't copied
+    assert_eq!(n_views(&conn, "main"), 1);
+}
+
+#[test]
+#[ignore]
+fn test_views_follow_prefixed_tables() {
+    let conn = copy("views_prefix", &["--schema", "public,archive"]);
+    assert_eq!(
+        rows(
+            &conn,
+            "SELECT * FROM public___release_downloads ORDER BY 1, 2"
+        ),
+        strings(&[&["1", "0", "1"], &["1", "1", "2"], &["2", "0", "1"]])
+    );
+    assert_eq!(
+        rows(&conn, "SELECT * FROM archive___release_downloads"),
+        strings(&[&["0", "2"]])
+    );
+    assert_eq!(
+        rows(
+            &conn,
+            "SELECT count(*) FROM public___archived_release WHERE NOT archived"
+        ),
+        strings(&[&["3"]])
+    );
+}
+
+#[test]
+#[ignore]
+fn test_views_in_attached_databases() {
+    let conn = copy(
+        "views_attach",
+        &["--schema", "public,archive", "--schema-naming", "attach"],
+    );
+    let attached = std::env::temp_dir().join("pg_to_sqlite3_views_attach.archive.db");
+    conn.execute(
+        "ATTACH DATABASE ? AS archive",
+        &[attached.to_str().unwrap()],
+    )
+    .unwrap();
+    assert_eq!(
+        rows(&conn, "SELECT * FROM archive._release_downloads"),
+        strings(&[&["0", "2"]])
+    );
+    // views can't reach into another database, so `_archived_release` is skipped
+    assert_eq!(n_views(&conn, "main"), 1);
+    assert_eq!(n_views(&conn, "archive"), 1);
+}
+
+#[test]
+#[ignore]
+fn test_printing_attachments_to_stdout() {
+    let out = Command::new(env!("CARGO_BIN_EXE_pg-to-sqlite3"))
+        .args(&["--src", &src(), "--dest", "stdout", "--schema-only"])
+        .args(&["--schema", "public,archive", "--schema-naming", "attach"])
+        .current_dir(std::env::temp_dir())
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let attach = stdout
+        .lines()
+        .find(|line| line.starts_with("ATTACH DATABASE"))
+        .unwrap();
+    assert!(!attach.contains("stdout"), "{}", attach);
+    assert!(
+        attach.contains(&*std::env::temp_dir().to_string_lossy()),
+        "{}",
+        attach
+    );
+}